@@ -65,7 +65,8 @@ fn append_to_passwdfile(file: &mut File, username: String, realm: String, passwo
     let hashed = Digest::simple_hashed_a1(&HashAlgorithm::MD5,
                                            Username::Plain(username.clone()),
                                            realm.clone(),
-                                           password);
+                                           password)
+        .expect("MD5 is a built-in algorithm");
     if let Err(failure) = write!(file, "{}:{}:{}\n", username, realm, hashed) {
         panic!(failure.to_string())
     }
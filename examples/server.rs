@@ -22,10 +22,12 @@ extern crate guardhaus;
 extern crate hyper;
 
 use guardhaus::digest::{Digest, Username};
+use guardhaus::error::ValidationError;
+use guardhaus::types::EntityBodyHasher;
 use hyper::header::Authorization;
 use hyper::server::{Request, Response, Server};
 use hyper::status::StatusCode;
-use std::io::Read;
+use std::io;
 
 const LISTEN: &'static str = "127.0.0.1:1337";
 const USERNAME: &'static str = "Spy";
@@ -33,25 +35,36 @@ const PASSWORD: &'static str = "vs. Spy";
 // const REALM: &'static str = "MadMag";
 
 fn needs_auth(mut req: Request, mut resp: Response) {
-    let mut entity_body = String::new();
-    if req.read_to_string(&mut entity_body).is_err() {
+    let auth = match req.headers.get::<Authorization<Digest>>() {
+        Some(auth) => auth.clone(),
+        None => {
+            *resp.status_mut() = StatusCode::Unauthorized;
+            return;
+        }
+    };
+    // Hash the entity body as it is read, instead of buffering it.
+    let mut hasher = match EntityBodyHasher::new(&auth.0.algorithm) {
+        Ok(hasher) => hasher,
+        Err(err) => {
+            *resp.status_mut() = ValidationError::from(err).status_code();
+            return;
+        }
+    };
+    if io::copy(&mut req, &mut hasher).is_err() {
         *resp.status_mut() = StatusCode::BadRequest;
         return;
     }
-    if let Some(auth) = req.headers.get::<Authorization<Digest>>() {
-        let username = Username::Plain(USERNAME.to_owned());
-        let password = PASSWORD.to_owned();
-        if auth.0.validate_using_userhash_and_password(
-                                                       req.method,
-                                                       entity_body,
-                                                       username,
-                                                       password) {
-            *resp.status_mut() = StatusCode::Ok;
-        } else {
-            *resp.status_mut() = StatusCode::Forbidden;
-        }
-    } else {
-        *resp.status_mut() = StatusCode::Unauthorized;
+    let username = Username::Plain(USERNAME.to_owned());
+    let password = PASSWORD.to_owned();
+    let result = auth.0.validate_request_uri(&req.uri).and_then(|_| {
+        auth.0.validate_using_userhash_password_and_entity_hash(req.method.clone(),
+                                                                &hasher.hex_digest(),
+                                                                username,
+                                                                password)
+    });
+    match result {
+        Ok(()) => *resp.status_mut() = StatusCode::Ok,
+        Err(err) => *resp.status_mut() = err.status_code(),
     }
 }
 
@@ -0,0 +1,182 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! Hash algorithms for the `algorithm` parameter that are not built into `HashAlgorithm`, such as
+//! `SHA3-256`.
+//!
+//! Implement `HashFunction` for the hash function, and register it with an `AlgorithmRegistry`.
+//! Headers are parsed without a registry, so algorithms that are not built in are parsed as
+//! `HashAlgorithm::Unknown`; `AlgorithmRegistry::resolve` turns the ones that were registered into
+//! `HashAlgorithm::Custom`, which can be used like the built-in algorithms.
+
+#![allow(bare_trait_objects)]
+
+use std::fmt;
+use std::sync::Arc;
+use super::types::HashAlgorithm;
+
+mod test;
+
+/// A cryptographic hash function that can be used by the `algorithm` parameter, both on its own
+/// and as its `-sess` variant.
+pub trait HashFunction: Send + Sync {
+    /// The name of the algorithm, without the `-sess` suffix (for example, `SHA3-256`).
+    fn name(&self) -> &str;
+
+    /// Creates a hasher for a single message.
+    fn hasher(&self) -> Box<Hasher>;
+
+    /// The strength of the hash function relative to the built-in ones, which are ranked `0`
+    /// (MD5), `1` (SHA-256), and `2` (SHA-512/256). Used by `Challenges::strongest_digest`.
+    fn strength(&self) -> u8 {
+        1
+    }
+}
+
+/// Incrementally hashes a message for a `HashFunction`.
+pub trait Hasher {
+    /// Hashes the next chunk of the message.
+    fn update(&mut self, data: &[u8]);
+
+    /// The output of the hash function for the entire message.
+    fn finish(self: Box<Self>) -> Vec<u8>;
+}
+
+/// A hash algorithm that was registered with an `AlgorithmRegistry`.
+#[derive(Clone)]
+pub struct CustomAlgorithm {
+    function: Arc<HashFunction>,
+    session: bool,
+}
+
+impl CustomAlgorithm {
+    /// The hash function of the algorithm.
+    pub fn function(&self) -> &HashFunction {
+        &*self.function
+    }
+
+    /// Whether this is the `-sess` variant of the algorithm.
+    pub fn is_session(&self) -> bool {
+        self.session
+    }
+
+    /// The algorithm without the `-sess` variant.
+    pub fn without_session(&self) -> CustomAlgorithm {
+        CustomAlgorithm {
+            function: self.function.clone(),
+            session: false,
+        }
+    }
+}
+
+impl fmt::Display for CustomAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.session {
+            write!(f, "{}-sess", self.function.name())
+        } else {
+            write!(f, "{}", self.function.name())
+        }
+    }
+}
+
+impl fmt::Debug for CustomAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CustomAlgorithm({})", self)
+    }
+}
+
+impl PartialEq for CustomAlgorithm {
+    fn eq(&self, other: &CustomAlgorithm) -> bool {
+        self.session == other.session && self.function.name() == other.function.name()
+    }
+}
+
+const BUILT_IN_ALGORITHMS: [HashAlgorithm; 6] = [HashAlgorithm::MD5,
+                                                 HashAlgorithm::MD5Session,
+                                                 HashAlgorithm::SHA256,
+                                                 HashAlgorithm::SHA256Session,
+                                                 HashAlgorithm::SHA512256,
+                                                 HashAlgorithm::SHA512256Session];
+
+/// The hash algorithms that an application supports: the built-in ones, and the ones that it
+/// registered.
+#[derive(Clone, Default)]
+pub struct AlgorithmRegistry {
+    functions: Vec<Arc<HashFunction>>,
+}
+
+impl AlgorithmRegistry {
+    /// Creates a registry that only contains the built-in algorithms.
+    pub fn new() -> AlgorithmRegistry {
+        AlgorithmRegistry::default()
+    }
+
+    /// Registers a hash function, replacing any registered function with the same name. Hash
+    /// functions with the name of a built-in algorithm are never used, because the built-in
+    /// algorithm takes precedence when parsing.
+    pub fn register<F: HashFunction + 'static>(&mut self, function: F) {
+        self.functions.retain(|registered| registered.name() != function.name());
+        self.functions.push(Arc::new(function));
+    }
+
+    /// Looks up an algorithm by its name, as used in the `algorithm` parameter.
+    pub fn get(&self, name: &str) -> Option<HashAlgorithm> {
+        match name.parse().map(|algorithm| self.resolve(&algorithm)) {
+            Ok(HashAlgorithm::Unknown(_)) |
+            Err(_) => None,
+            Ok(algorithm) => Some(algorithm),
+        }
+    }
+
+    /// Resolves an unknown algorithm into a registered one, if there is one with its name.
+    /// Otherwise, returns a copy of `algorithm`.
+    pub fn resolve(&self, algorithm: &HashAlgorithm) -> HashAlgorithm {
+        if let HashAlgorithm::Unknown(ref name) = *algorithm {
+            let (base, session) = if name.ends_with("-sess") {
+                (&name[..name.len() - 5], true)
+            } else {
+                (&name[..], false)
+            };
+            if let Some(function) = self.functions.iter().find(|f| f.name() == base) {
+                return HashAlgorithm::Custom(CustomAlgorithm {
+                    function: function.clone(),
+                    session: session,
+                });
+            }
+        }
+        algorithm.clone()
+    }
+
+    /// Every supported algorithm, built-in ones first, each followed by its `-sess` variant. A
+    /// server can offer a challenge for each of them.
+    pub fn algorithms(&self) -> Vec<HashAlgorithm> {
+        let mut algorithms = BUILT_IN_ALGORITHMS.to_vec();
+        for function in &self.functions {
+            for session in &[false, true] {
+                algorithms.push(HashAlgorithm::Custom(CustomAlgorithm {
+                    function: function.clone(),
+                    session: *session,
+                }));
+            }
+        }
+        algorithms
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use client::DigestClient;
+use credentials::{CredentialStore, InMemoryCredentialStore};
+use crypto_hash;
+use digest::Username;
+use digest::test_helper::rfc2617_digest_header;
+use error::ValidationError;
+use hyper::method::Method;
+use hyper::status::StatusCode;
+use std::io::Write;
+use super::{AlgorithmRegistry, HashFunction, Hasher};
+use super::super::types::{HashAlgorithm, Qop};
+use www_authenticate::WwwAuthenticate;
+
+const PASSWORD: &'static str = "Circle Of Life";
+
+/// MD5 under another name, so that the expected digests are known.
+struct RenamedMD5;
+
+struct RenamedMD5Hasher(crypto_hash::Hasher);
+
+impl HashFunction for RenamedMD5 {
+    fn name(&self) -> &str {
+        "RENAMED-MD5"
+    }
+
+    #[allow(bare_trait_objects)]
+    fn hasher(&self) -> Box<Hasher> {
+        Box::new(RenamedMD5Hasher(crypto_hash::Hasher::new(crypto_hash::Algorithm::MD5)))
+    }
+}
+
+impl Hasher for RenamedMD5Hasher {
+    fn update(&mut self, data: &[u8]) {
+        self.0.write_all(data).expect("Could not hash data")
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        let mut hasher = self.0;
+        hasher.finish()
+    }
+}
+
+fn registry() -> AlgorithmRegistry {
+    let mut registry = AlgorithmRegistry::new();
+    registry.register(RenamedMD5);
+    registry
+}
+
+#[test]
+fn test_resolve() {
+    let registry = registry();
+    let algorithm = registry.resolve(&HashAlgorithm::Unknown("RENAMED-MD5".to_owned()));
+    assert!(algorithm.is_supported());
+    assert!(!algorithm.is_session());
+    assert_eq!("RENAMED-MD5", algorithm.to_string());
+
+    let session = registry.resolve(&HashAlgorithm::Unknown("RENAMED-MD5-sess".to_owned()));
+    assert!(session.is_session());
+    assert_eq!("RENAMED-MD5-sess", session.to_string());
+    assert_eq!(algorithm, session.hash_function());
+
+    let unknown = HashAlgorithm::Unknown("SHA3-256".to_owned());
+    assert_eq!(unknown, registry.resolve(&unknown));
+    assert_eq!(HashAlgorithm::SHA256, registry.resolve(&HashAlgorithm::SHA256));
+}
+
+#[test]
+fn test_get() {
+    let registry = registry();
+    assert_eq!(Some(HashAlgorithm::MD5Session), registry.get("MD5-sess"));
+    assert_eq!(registry.get("RENAMED-MD5-sess").map(|algorithm| algorithm.to_string()),
+               Some("RENAMED-MD5-sess".to_owned()));
+    assert_eq!(None, registry.get("SHA3-256"));
+    assert_eq!(None, AlgorithmRegistry::new().get("RENAMED-MD5"));
+}
+
+#[test]
+fn test_algorithms() {
+    let algorithms: Vec<String> = registry().algorithms().iter().map(|a| a.to_string()).collect();
+    assert_eq!(vec!["MD5",
+                    "MD5-sess",
+                    "SHA-256",
+                    "SHA-256-sess",
+                    "SHA-512-256",
+                    "SHA-512-256-sess",
+                    "RENAMED-MD5",
+                    "RENAMED-MD5-sess"],
+               algorithms);
+}
+
+#[test]
+fn test_register_replaces_function_with_same_name() {
+    let mut registry = registry();
+    registry.register(RenamedMD5);
+    assert_eq!(8, registry.algorithms().len());
+}
+
+#[test]
+fn test_hex_digest_with_custom_algorithm() {
+    let algorithm = registry().get("RENAMED-MD5").expect("Could not find algorithm");
+    assert_eq!(HashAlgorithm::MD5.hex_digest(b"data".to_vec()).expect("Could not hash"),
+               algorithm.hex_digest(b"data".to_vec()).expect("Could not hash"));
+}
+
+#[test]
+fn test_validate_with_custom_algorithm() {
+    let unknown = HashAlgorithm::Unknown("RENAMED-MD5".to_owned());
+    let digest = rfc2617_digest_header(registry().resolve(&unknown));
+    assert_eq!(Ok(()),
+               digest.validate_using_password(Method::Get, b"", PASSWORD.to_owned()));
+}
+
+#[test]
+fn test_validate_with_unknown_algorithm() {
+    let digest = rfc2617_digest_header(HashAlgorithm::Unknown("RENAMED-MD5".to_owned()));
+    let err = digest.validate_using_password(Method::Get, b"", PASSWORD.to_owned()).unwrap_err();
+    assert_eq!(ValidationError::UnsupportedAlgorithm("RENAMED-MD5".to_owned()), err);
+    assert_eq!(StatusCode::Unauthorized, err.status_code());
+    assert!(!digest.validate_userhash(Username::Plain("Mufasa".to_owned())));
+}
+
+#[test]
+fn test_client_with_custom_session_algorithm() {
+    let challenge = WwwAuthenticate {
+        realm: "testrealm@host.com".to_owned(),
+        domain: vec![],
+        nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_owned(),
+        opaque: None,
+        stale: false,
+        algorithm: registry().get("RENAMED-MD5-sess").expect("Could not find algorithm"),
+        qop: vec![Qop::Auth],
+        charset: None,
+        userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
+    };
+    let mut client = DigestClient::new(challenge, "Mufasa".to_owned(), PASSWORD.to_owned());
+    let digest = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate authorization")
+        .0;
+    assert!(digest.client_nonce.is_some());
+    assert_eq!(Ok(()),
+               digest.validate_using_password(Method::Get, b"", PASSWORD.to_owned()));
+}
+
+#[test]
+fn test_in_memory_username_for_custom_userhash() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_password("Mufasa", "testrealm@host.com", PASSWORD);
+    let challenge = WwwAuthenticate {
+        realm: "testrealm@host.com".to_owned(),
+        domain: vec![],
+        nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_owned(),
+        opaque: None,
+        stale: false,
+        algorithm: registry().get("RENAMED-MD5").expect("Could not find algorithm"),
+        qop: vec![Qop::Auth],
+        charset: None,
+        userhash: true,
+        extensions: WwwAuthenticate::new_extensions(),
+    };
+    let mut client = DigestClient::new(challenge, "Mufasa".to_owned(), PASSWORD.to_owned());
+    let digest = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate authorization")
+        .0;
+    let userhash = digest.username.to_string();
+    assert_eq!(Some("Mufasa".to_owned()),
+               store.username_for_userhash(&userhash, "testrealm@host.com", &digest.algorithm));
+    assert_eq!(Ok(()), digest.validate_using_credential_store(Method::Get, b"", &store));
+}
@@ -20,14 +20,16 @@
 
 //! An implementation of the `Authentication-Info` header.
 
+use error::Error;
 use hyper::{Error as HyperError, Result as HyperResult};
 use hyper::header::{Header, HeaderFormat};
 use hyper::header::parsing::from_one_raw_str;
-use parsing::{append_parameter, parse_parameters, unraveled_map_value};
+use parsing::{append_extensions, append_parameter, parameter_value, parse_parameters,
+              Declarations, Decoding};
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
-use super::types::{NonceCount, Qop};
+use super::types::{ExtensionParameters, NonceCount, Qop};
 use unicase::UniCase;
 
 mod test;
@@ -46,42 +48,69 @@ pub struct AuthenticationInfo {
     pub client_nonce: Option<String>,
     /// Nonce count, parameter name `nc`
     pub nonce_count: Option<NonceCount>,
+    /// Extension parameters, which are not defined by RFC 7616.
+    pub extensions: ExtensionParameters,
 }
 
-fn parse_digest(map: &HashMap<UniCase<String>, String>) -> Result<Option<String>, HyperError> {
-    if let Some(rspauth) = unraveled_map_value(map, "rspauth") {
-        if unraveled_map_value(map, "digest").is_some() {
-            Err(HyperError::Header)
+/// The parameters of the `Authentication-Info` header, as described in
+/// [RFC 7616, section 3.5](https://tools.ietf.org/html/rfc7616#section-3.5). `digest` is the
+/// [RFC 2069](https://tools.ietf.org/html/rfc2069#section-2.1.3) name of `rspauth`.
+const PARAMETERS: &'static Declarations = &[("rspauth", Decoding::QuotedString),
+                                             ("digest", Decoding::QuotedString),
+                                             ("nextnonce", Decoding::QuotedString),
+                                             ("qop", Decoding::Token),
+                                             ("cnonce", Decoding::QuotedString),
+                                             ("nc", Decoding::Token)];
+
+fn parse_digest(map: &HashMap<UniCase<String>, String>) -> Result<Option<String>, Error> {
+    if let Some(rspauth) = parameter_value(map, "rspauth") {
+        if parameter_value(map, "digest").is_some() {
+            Err(Error::ConflictingParameters("rspauth", "digest"))
         } else {
             Ok(Some(rspauth))
         }
-    } else if let Some(digest) = unraveled_map_value(map, "digest") {
+    } else if let Some(digest) = parameter_value(map, "digest") {
         Ok(Some(digest))
     } else {
         Ok(None)
     }
 }
 
+impl AuthenticationInfo {
+    /// Creates an empty collection of extension parameters, in which the parameters of RFC 7616
+    /// are reserved.
+    pub fn new_extensions() -> ExtensionParameters {
+        ExtensionParameters::new(PARAMETERS)
+    }
+}
+
 impl FromStr for AuthenticationInfo {
-    type Err = HyperError;
+    type Err = Error;
 
-    fn from_str(s: &str) -> Result<AuthenticationInfo, HyperError> {
-        let parameters = parse_parameters(s);
+    fn from_str(s: &str) -> Result<AuthenticationInfo, Error> {
+        let (parameters, extensions) = parse_parameters(s, PARAMETERS)?;
         let digest = parse_digest(&parameters)?;
         let qop = Qop::from_parameters(&parameters)?;
-        let client_nonce = unraveled_map_value(&parameters, "cnonce");
+        let client_nonce = parameter_value(&parameters, "cnonce");
         let nonce_count = NonceCount::from_parameters(&parameters)?;
 
-        if qop.is_some() && (digest.is_none() || client_nonce.is_none() || nonce_count.is_none()) {
-            return Err(HyperError::Header);
+        if qop.is_some() {
+            if digest.is_none() {
+                return Err(Error::MissingParameter("rspauth"));
+            } else if client_nonce.is_none() {
+                return Err(Error::MissingParameter("cnonce"));
+            } else if nonce_count.is_none() {
+                return Err(Error::MissingParameter("nc"));
+            }
         }
 
         Ok(AuthenticationInfo {
             digest: digest,
-            next_nonce: unraveled_map_value(&parameters, "nextnonce"),
+            next_nonce: parameter_value(&parameters, "nextnonce"),
             qop: qop,
             client_nonce: client_nonce,
             nonce_count: nonce_count,
+            extensions: extensions,
         })
     }
 }
@@ -92,7 +121,8 @@ impl Header for AuthenticationInfo {
     }
 
     fn parse_header(raw: &[Vec<u8>]) -> HyperResult<AuthenticationInfo> {
-        from_one_raw_str(raw).and_then(|s: String| AuthenticationInfo::from_str(&s[..]))
+        from_one_raw_str(raw)
+            .and_then(|s: String| AuthenticationInfo::from_str(&s[..]).map_err(HyperError::from))
     }
 }
 
@@ -120,6 +150,8 @@ impl HeaderFormat for AuthenticationInfo {
             append_parameter(&mut serialized, "nc", &nonce_count.to_string(), false);
         }
 
+        append_extensions(&mut serialized, &self.extensions);
+
         write!(f, "{}", serialized)
     }
 }
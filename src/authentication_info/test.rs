@@ -20,6 +20,8 @@
 
 #![cfg(test)]
 
+use error::Error;
+use hyper::header::Header;
 use parsing::test_helper::{assert_header_parsing_error, assert_parsed_header_equal,
                            assert_serialized_header_equal};
 use super::AuthenticationInfo;
@@ -33,6 +35,7 @@ fn test_parse_authentication_info_with_digest_and_nextnonce() {
         qop: None,
         client_nonce: None,
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_parsed_header_equal(expected, "nextnonce=\"fedcba\", rspauth=\"abcdef\"");
 }
@@ -45,6 +48,7 @@ fn test_parse_authentication_info_with_digest() {
         qop: None,
         client_nonce: None,
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_parsed_header_equal(expected, "rspauth=\"abcdef\"");
 }
@@ -62,6 +66,7 @@ fn test_parse_authentication_info_with_qop() {
         qop: Some(Qop::Auth),
         client_nonce: Some("1234".to_owned()),
         nonce_count: Some(NonceCount(2)),
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_parsed_header_equal(expected,
                                "qop=auth, rspauth=\"abcdef\", cnonce=\"1234\", nc=00000002");
@@ -78,6 +83,12 @@ fn test_parse_authentication_info_with_bad_qop() {
     assert_header_parsing_error::<AuthenticationInfo>("qop=invalid");
 }
 
+#[test]
+fn test_parse_authentication_info_with_multiple_qops() {
+    assert_header_parsing_error::<AuthenticationInfo>("qop=\"auth,auth-int\", rspauth=\"abcdef\", \
+                                                       cnonce=\"1234\", nc=00000002");
+}
+
 #[test]
 fn test_parse_authentication_info_with_nextnonce() {
     let expected = AuthenticationInfo {
@@ -86,6 +97,7 @@ fn test_parse_authentication_info_with_nextnonce() {
         qop: None,
         client_nonce: None,
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_parsed_header_equal(expected, "nextnonce=\"fedcba\"");
 }
@@ -103,6 +115,7 @@ fn test_parse_authentication_info_with_digest_qop_cnonce_and_nc() {
         qop: Some(Qop::Auth),
         client_nonce: Some("client nonce".to_owned()),
         nonce_count: Some(NonceCount(1)),
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_parsed_header_equal(expected,
                                "qop=auth, rspauth=\"abcdef\", cnonce=\"client nonce\", \
@@ -117,6 +130,7 @@ fn test_fmt_authentication_info_with_digest_and_nextnonce() {
         qop: None,
         client_nonce: None,
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_serialized_header_equal(header,
                                    "Authentication-Info: rspauth=\"abcdef\", nextnonce=\"fedcba\"");
@@ -130,6 +144,7 @@ fn test_fmt_authentication_info_with_digest() {
         qop: None,
         client_nonce: None,
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_serialized_header_equal(header, "Authentication-Info: rspauth=\"abcdef\"");
 }
@@ -142,6 +157,7 @@ fn test_fmt_authentication_info_with_nextnonce() {
         qop: None,
         client_nonce: None,
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_serialized_header_equal(header, "Authentication-Info: nextnonce=\"fedcba\"");
 }
@@ -154,6 +170,7 @@ fn test_fmt_authentication_info_with_qop() {
         qop: Some(Qop::AuthInt),
         client_nonce: None,
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_serialized_header_equal(header, "Authentication-Info: qop=auth-int");
 }
@@ -166,6 +183,7 @@ fn test_fmt_authentication_info_with_client_nonce() {
         qop: None,
         client_nonce: Some("client nonce".to_owned()),
         nonce_count: None,
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_serialized_header_equal(header, "Authentication-Info: cnonce=\"client nonce\"");
 }
@@ -178,6 +196,27 @@ fn test_fmt_authentication_info_with_nonce_count() {
         qop: None,
         client_nonce: None,
         nonce_count: Some(NonceCount(0xff)),
+        extensions: AuthenticationInfo::new_extensions(),
     };
     assert_serialized_header_equal(header, "Authentication-Info: nc=000000ff");
 }
+
+#[test]
+fn test_authentication_info_round_trip_with_extensions() {
+    let data = "rspauth=\"abcdef\", nextnonce=\"fedcba\", x-trace=42, x-note=\"a b\"";
+    let header: AuthenticationInfo = Header::parse_header(&[data.to_owned().into_bytes()][..])
+        .expect("Could not parse Authentication-Info header");
+    assert_eq!(Some("42"), header.extensions.get("X-Trace"));
+    assert_eq!(Some("a b"), header.extensions.get("x-note"));
+    assert_serialized_header_equal(header, &format!("Authentication-Info: {}", data)[..])
+}
+
+#[test]
+fn test_authentication_info_extensions_cannot_shadow_parameters() {
+    let mut extensions = AuthenticationInfo::new_extensions();
+    assert_eq!(Err(Error::ReservedParameter("NextNonce".to_owned())),
+               extensions.insert("NextNonce", "abc"));
+    assert_eq!(Err(Error::ReservedParameter("digest".to_owned())),
+               extensions.insert("digest", "abc"));
+    assert!(extensions.is_empty())
+}
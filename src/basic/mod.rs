@@ -0,0 +1,184 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! An HTTP Basic implementation for [Hyper](http://hyper.rs)'s `Authorization` header, and the
+//! corresponding `WWW-Authenticate` challenge.
+//!
+//! The scheme is described in [RFC 7617](https://tools.ietf.org/html/rfc7617).
+
+use constant_time;
+use crypto_hash::{Algorithm, digest};
+use error::Error;
+use hyper::{Error as HyperError, Result as HyperResult};
+use hyper::header::{Charset, Header, HeaderFormat, Scheme};
+use hyper::header::parsing::from_one_raw_str;
+use parsing::{append_parameter, is_token68, parameter_value, parse_charset, parse_parameters,
+              strip_scheme, Declarations, Decoding};
+use rustc_serialize::base64::{FromBase64, STANDARD, ToBase64};
+use std::fmt;
+use std::str::FromStr;
+
+mod test;
+
+/// Credentials for the `Authorization` header when using the `Basic` scheme.
+///
+/// The credentials are described in more detail in
+/// [RFC 7617, section 2](https://tools.ietf.org/html/rfc7617#section-2).
+///
+/// The user-id cannot contain a colon, because the first colon separates it from the password.
+/// `Basic::new` checks this; credentials whose user-id contains a colon are serialized as is, and
+/// would be parsed back with the rest of the user-id prepended to the password.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Basic {
+    /// The user-id. Cannot contain a colon.
+    pub username: String,
+    /// The password. Unlike the user-id, it can contain colons.
+    pub password: String,
+}
+
+impl Scheme for Basic {
+    fn scheme() -> Option<&'static str> {
+        Some("Basic")
+    }
+
+    fn fmt_scheme(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let user_pass = format!("{}:{}", self.username, self.password);
+        write!(f, "{}", user_pass.as_bytes().to_base64(STANDARD))
+    }
+}
+
+impl FromStr for Basic {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Basic, Error> {
+        let token68 = s.trim();
+        if !is_token68(token68) {
+            return Err(Error::MalformedCredentials);
+        }
+        let decoded = match token68.from_base64() {
+            Ok(bytes) => bytes,
+            Err(_) => return Err(Error::MalformedCredentials),
+        };
+        let user_pass = match String::from_utf8(decoded) {
+            Ok(value) => value,
+            Err(_) => return Err(Error::MalformedCredentials),
+        };
+        // The user-id cannot contain a colon, so the first one is the delimiter.
+        let mut parts = user_pass.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(username), Some(password)) => {
+                Ok(Basic {
+                    username: username.to_owned(),
+                    password: password.to_owned(),
+                })
+            }
+            _ => Err(Error::MalformedCredentials),
+        }
+    }
+}
+
+fn sha256(value: &str) -> Vec<u8> {
+    digest(Algorithm::SHA256, value.as_bytes().to_vec())
+}
+
+impl Basic {
+    /// Creates credentials, given the user-id and password. Returns
+    /// `Error::DisallowedCharacter(':')` if the user-id contains a colon.
+    pub fn new(username: String, password: String) -> Result<Basic, Error> {
+        if username.contains(':') {
+            return Err(Error::DisallowedCharacter(':'));
+        }
+        Ok(Basic {
+            username: username,
+            password: password,
+        })
+    }
+
+    /// Validates the credentials, given the expected user-id and password.
+    ///
+    /// The credentials are compared in constant time. Because the lengths of the user-id and
+    /// password are secret too, their SHA-256 digests are compared instead of the values.
+    pub fn validate(&self, username: &str, password: &str) -> bool {
+        let username_matches = constant_time::eq(&sha256(&self.username), &sha256(username));
+        let password_matches = constant_time::eq(&sha256(&self.password), &sha256(password));
+        username_matches & password_matches
+    }
+}
+
+/// Parameters for the `WWW-Authenticate` header when using the `Basic` scheme.
+///
+/// The parameters are described in more detail in
+/// [RFC 7617, section 2](https://tools.ietf.org/html/rfc7617#section-2).
+#[derive(Clone, PartialEq, Debug)]
+pub struct BasicChallenge {
+    /// Authentication realm.
+    pub realm: String,
+    /// The character set that the server expects the client to use when encoding the user-id and
+    /// password. The only allowed value is `UTF-8`.
+    pub charset: Option<Charset>,
+}
+
+/// The parameters of a `Basic` challenge, as described in
+/// [RFC 7617, section 2](https://tools.ietf.org/html/rfc7617#section-2).
+const CHALLENGE_PARAMETERS: &'static Declarations = &[("realm", Decoding::QuotedString),
+                                                       ("charset", Decoding::QuotedString)];
+
+impl FromStr for BasicChallenge {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<BasicChallenge, Error> {
+        let (parameters, _) = match strip_scheme(s, "Basic") {
+            Some(value) => parse_parameters(value, CHALLENGE_PARAMETERS)?,
+            None => return Err(Error::UnexpectedScheme("Basic")),
+        };
+        let realm = match parameter_value(&parameters, "realm") {
+            Some(value) => value,
+            None => return Err(Error::MissingParameter("realm")),
+        };
+        let charset = parse_charset(&parameters)?;
+
+        Ok(BasicChallenge {
+            realm: realm,
+            charset: charset,
+        })
+    }
+}
+
+impl Header for BasicChallenge {
+    fn header_name() -> &'static str {
+        "WWW-Authenticate"
+    }
+
+    fn parse_header(raw: &[Vec<u8>]) -> HyperResult<BasicChallenge> {
+        from_one_raw_str(raw)
+            .and_then(|s: String| BasicChallenge::from_str(&s[..]).map_err(HyperError::from))
+    }
+}
+
+impl HeaderFormat for BasicChallenge {
+    fn fmt_header(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut serialized = String::new();
+        append_parameter(&mut serialized, "realm", &self.realm, true);
+        if let Some(ref charset) = self.charset {
+            append_parameter(&mut serialized, "charset", &charset.to_string(), true);
+        }
+        write!(f, "Basic {}", serialized)
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Generates `Authorization` headers for the `Digest` scheme, in response to a `WWW-Authenticate`
+//! challenge from a server.
+
+use authentication_info::AuthenticationInfo;
+use digest::{Digest, Username};
+use error::Error;
+use hex::ToHex;
+use hyper::header::{Authorization, Charset};
+use hyper::header::parsing::ExtendedValue;
+use hyper::method::Method;
+use parsing::is_utf8;
+use precis::Profile;
+use rand::{Rng, thread_rng};
+use std::fmt;
+use super::types::{EntityBodyHasher, NonceCount, Qop};
+use unicode_normalization::UnicodeNormalization;
+use www_authenticate::WwwAuthenticate;
+
+mod test;
+
+const CLIENT_NONCE_LENGTH: usize = 16;
+
+fn generate_client_nonce() -> String {
+    let mut bytes = [0u8; CLIENT_NONCE_LENGTH];
+    thread_rng().fill_bytes(&mut bytes);
+    bytes.to_hex()
+}
+
+/// Whether a username cannot be sent as-is in the `username` parameter.
+fn needs_encoding(username: &str) -> bool {
+    username.bytes().any(|byte| byte >= 0x80 || byte == b'"' || byte == b'\\')
+}
+
+/// Generates `Authorization` headers for requests to a server that sent a `Digest` challenge.
+///
+/// The client keeps track of the nonce count, so the same instance should be used for every
+/// request that responds to the same challenge.
+#[derive(Clone)]
+pub struct DigestClient {
+    username: String,
+    password: String,
+    challenge: WwwAuthenticate,
+    client_nonce: String,
+    nonce_count: u32,
+    preferred_qop: Option<Qop>,
+    username_profile: Option<Profile>,
+}
+
+impl fmt::Debug for DigestClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DigestClient")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("challenge", &self.challenge)
+            .field("client_nonce", &self.client_nonce)
+            .field("nonce_count", &self.nonce_count)
+            .field("preferred_qop", &self.preferred_qop)
+            .field("username_profile", &self.username_profile)
+            .finish()
+    }
+}
+
+impl DigestClient {
+    /// Creates a client, given a challenge from the server and the user's credentials.
+    pub fn new(challenge: WwwAuthenticate, username: String, password: String) -> DigestClient {
+        DigestClient {
+            username: username,
+            password: password,
+            challenge: challenge,
+            client_nonce: generate_client_nonce(),
+            nonce_count: 0,
+            preferred_qop: None,
+            username_profile: None,
+        }
+    }
+
+    /// The challenge that the client is responding to.
+    pub fn challenge(&self) -> &WwwAuthenticate {
+        &self.challenge
+    }
+
+    /// Responds to a new challenge from the server, for example, when the server indicates that
+    /// the previous nonce is stale. If the nonce changed, the nonce count is reset.
+    pub fn set_challenge(&mut self, challenge: WwwAuthenticate) {
+        if challenge.nonce != self.challenge.nonce {
+            self.reset_client_nonce();
+        }
+        self.challenge = challenge;
+    }
+
+    fn reset_client_nonce(&mut self) {
+        self.client_nonce = generate_client_nonce();
+        self.nonce_count = 0;
+    }
+
+    /// Verifies the `Authentication-Info` header that the server sent in response to a request
+    /// made with `authorization`, which proves that the server also knows the user's password.
+    ///
+    /// If the header is valid and contains a `nextnonce`, it is used for subsequent requests.
+    /// `response_body` is only used when the quality of protection is `auth-int`.
+    pub fn verify_authentication_info(&mut self,
+                                      authorization: &Authorization<Digest>,
+                                      info: &AuthenticationInfo,
+                                      response_body: &[u8])
+                                      -> bool {
+        let (username, password) = match self.credentials() {
+            Ok(credentials) => credentials,
+            Err(_) => return false,
+        };
+        if !authorization.0
+            .validate_authentication_info(info, response_body, self.username(username), password) {
+            return false;
+        }
+        if let Some(ref next_nonce) = info.next_nonce {
+            if *next_nonce != self.challenge.nonce {
+                self.challenge.nonce = next_nonce.clone();
+                self.reset_client_nonce();
+            }
+        }
+        true
+    }
+
+    /// Uses the given quality of protection whenever the challenge offers it. Otherwise, the
+    /// first quality of protection in the challenge is used.
+    pub fn prefer_qop(&mut self, qop: Qop) {
+        self.preferred_qop = Some(qop);
+    }
+
+    /// Prepares the username with the given PRECIS profile, and the password with the
+    /// `OpaqueString` profile, whenever the challenge's `charset` is `UTF-8`. See the `precis`
+    /// module for details.
+    pub fn use_precis(&mut self, profile: Profile) {
+        self.username_profile = Some(profile);
+    }
+
+    fn qop(&self) -> Option<Qop> {
+        match self.preferred_qop {
+            Some(ref preferred) if self.challenge.qop.contains(preferred) => {
+                Some(preferred.clone())
+            }
+            _ => self.challenge.qop.first().cloned(),
+        }
+    }
+
+    fn charset(&self) -> Option<Charset> {
+        match self.challenge.charset {
+            Some(ref charset) if is_utf8(charset) => Some(Charset::Ext("UTF-8".to_owned())),
+            _ => None,
+        }
+    }
+
+    /// The username and password, prepared for the challenge's `charset`. If the challenge
+    /// requested `UTF-8`, the username is normalized to Unicode Normalization Form C (or prepared
+    /// with its PRECIS profile, if one was chosen), so that it matches the username that the
+    /// server uses in the A1 value.
+    fn credentials(&self) -> Result<(String, String), Error> {
+        match (self.charset(), self.username_profile) {
+            (Some(_), Some(profile)) => {
+                Ok((profile.enforce(&self.username)?,
+                    Profile::OpaqueString.enforce(&self.password)?))
+            }
+            (Some(_), None) => Ok((self.username.nfc().collect(), self.password.clone())),
+            (None, _) => Ok((self.username.clone(), self.password.clone())),
+        }
+    }
+
+    fn username(&self, username: String) -> Username {
+        if needs_encoding(&username) {
+            Username::Encoded(ExtendedValue {
+                charset: Charset::Ext("UTF-8".to_owned()),
+                language_tag: None,
+                value: username.into_bytes(),
+            })
+        } else {
+            Username::Plain(username)
+        }
+    }
+
+    fn header_username(&self, username: String) -> Result<Username, Error> {
+        if self.challenge.userhash {
+            let userhash = Digest::userhash(&self.challenge.algorithm,
+                                            username.into_bytes(),
+                                            self.challenge.realm.clone())?;
+            Ok(Username::Plain(userhash))
+        } else {
+            Ok(self.username(username))
+        }
+    }
+
+    /// Generates the `Authorization` header for an HTTP request, using the algorithm requested by
+    /// the challenge and the selected quality of protection. Each call increments the nonce count.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn authorization(&mut self,
+                         method: Method,
+                         request_uri: &str,
+                         entity_body: &[u8])
+                         -> Result<Authorization<Digest>, Error> {
+        let entity_hash = if self.qop() == Some(Qop::AuthInt) {
+            let mut hasher = EntityBodyHasher::new(&self.challenge.algorithm)?;
+            hasher.update(entity_body);
+            hasher.hex_digest()
+        } else {
+            String::new()
+        };
+        self.authorization_with_entity_hash(method, request_uri, &entity_hash)
+    }
+
+    /// Like `authorization`, but given the hexadecimal hash of the entity body instead of the
+    /// entity body itself. The hash must be generated via an `EntityBodyHasher` that uses the
+    /// challenge's algorithm.
+    pub fn authorization_with_entity_hash(&mut self,
+                                          method: Method,
+                                          request_uri: &str,
+                                          entity_hash: &str)
+                                          -> Result<Authorization<Digest>, Error> {
+        let algorithm = self.challenge.algorithm.clone();
+        let session = algorithm.is_session();
+        let (username, password) = self.credentials()?;
+        let header_username = self.header_username(username.clone())?;
+        let qop = self.qop();
+        let (nonce_count, client_nonce) = if qop.is_some() {
+            self.nonce_count += 1;
+            (Some(NonceCount(self.nonce_count)), Some(self.client_nonce.clone()))
+        } else if session {
+            (None, Some(self.client_nonce.clone()))
+        } else {
+            (None, None)
+        };
+        let mut digest = Digest {
+            username: header_username,
+            realm: self.challenge.realm.clone(),
+            nonce: self.challenge.nonce.clone(),
+            nonce_count: nonce_count,
+            response: String::new(),
+            request_uri: request_uri.to_owned(),
+            algorithm: algorithm,
+            qop: qop,
+            client_nonce: client_nonce,
+            opaque: self.challenge.opaque.clone(),
+            charset: self.charset(),
+            userhash: self.challenge.userhash,
+            extensions: Digest::new_extensions(),
+        };
+        digest.response = digest.using_username_password_and_entity_hash(method,
+                                                                          entity_hash,
+                                                                          self.username(username),
+                                                                          password)?;
+        Ok(Authorization(digest))
+    }
+}
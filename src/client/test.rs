@@ -0,0 +1,368 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use authentication_info::AuthenticationInfo;
+use digest::Username;
+use error::{Error, ValidationError};
+use hyper::header::Charset;
+use hyper::method::Method;
+use precis::Profile;
+use super::{DigestClient, generate_client_nonce};
+use super::super::types::{EntityBodyHasher, HashAlgorithm, NonceCount, Qop};
+use www_authenticate::WwwAuthenticate;
+
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.1
+fn rfc7616_3_9_1_client(algorithm: HashAlgorithm) -> DigestClient {
+    let challenge = WwwAuthenticate {
+        realm: "http-auth@example.org".to_owned(),
+        domain: vec![],
+        nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v".to_owned(),
+        opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
+        stale: false,
+        algorithm: algorithm,
+        qop: vec![Qop::Auth],
+        charset: None,
+        userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
+    };
+    let mut client = DigestClient::new(challenge, "Mufasa".to_owned(), "Circle of Life".to_owned());
+    client.client_nonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ".to_owned();
+    client
+}
+
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.2
+fn rfc7616_3_9_2_client() -> DigestClient {
+    let challenge = WwwAuthenticate {
+        realm: "api@example.org".to_owned(),
+        domain: vec![],
+        nonce: "5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK".to_owned(),
+        opaque: Some("HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS".to_owned()),
+        stale: false,
+        algorithm: HashAlgorithm::SHA512256,
+        qop: vec![Qop::Auth],
+        charset: Some(Charset::Ext("UTF-8".to_owned())),
+        userhash: true,
+        extensions: WwwAuthenticate::new_extensions(),
+    };
+    let mut client = DigestClient::new(challenge,
+                                       "J\u{e4}s\u{f8}n Doe".to_owned(),
+                                       "Secret, or not?".to_owned());
+    client.client_nonce = "NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v".to_owned();
+    client
+}
+
+#[test]
+fn test_authorization_with_md5() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!(Username::Plain("Mufasa".to_owned()), header.0.username);
+    assert_eq!(Some(NonceCount(1)), header.0.nonce_count);
+    assert_eq!(Some(Qop::Auth), header.0.qop);
+    assert_eq!("8ca523f5e9506fed4657c9700eebdbec", header.0.response);
+    assert_eq!(client.challenge().opaque, header.0.opaque)
+}
+
+#[test]
+fn test_authorization_with_sha256() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::SHA256);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!("753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+               header.0.response);
+    assert!(header.0.validate_using_password(Method::Get,
+                                             b"",
+                                             "Circle of Life".to_owned()).is_ok())
+}
+
+#[test]
+fn test_authorization_increments_nonce_count() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let first = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    let second = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!(Some(NonceCount(1)), first.0.nonce_count);
+    assert_eq!(Some(NonceCount(2)), second.0.nonce_count);
+    assert!(first.0.response != second.0.response)
+}
+
+#[test]
+fn test_authorization_with_auth_int() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let mut challenge = client.challenge().clone();
+    challenge.qop = vec![Qop::AuthInt];
+    client.set_challenge(challenge);
+    let header = client.authorization(Method::Post, "/dir/index.html", b"foo=bar")
+        .expect("Could not generate digest");
+    assert_eq!(Some(Qop::AuthInt), header.0.qop);
+    assert!(header.0.validate_using_password(Method::Post,
+                                             b"foo=bar",
+                                             "Circle of Life".to_owned()).is_ok());
+    assert_eq!(Err(ValidationError::WrongCredentials),
+               header.0.validate_using_password(Method::Post,
+                                                b"foo=baz",
+                                                "Circle of Life".to_owned()))
+}
+
+#[test]
+fn test_authorization_sans_qop() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let mut challenge = client.challenge().clone();
+    challenge.qop = vec![];
+    client.set_challenge(challenge);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!(None, header.0.nonce_count);
+    assert_eq!(None, header.0.client_nonce);
+    assert!(header.0.validate_using_password(Method::Get,
+                                             b"",
+                                             "Circle of Life".to_owned()).is_ok())
+}
+
+#[test]
+fn test_authorization_with_session_algorithm_sans_qop() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5Session);
+    let mut challenge = client.challenge().clone();
+    challenge.qop = vec![];
+    client.set_challenge(challenge);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!(None, header.0.nonce_count);
+    assert!(header.0.client_nonce.is_some());
+    assert!(header.0.validate_using_password(Method::Get,
+                                             b"",
+                                             "Circle of Life".to_owned()).is_ok())
+}
+
+#[test]
+fn test_set_challenge_with_new_nonce_resets_nonce_count() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    let mut challenge = client.challenge().clone();
+    challenge.nonce = "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_owned();
+    challenge.stale = true;
+    client.set_challenge(challenge);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!("dcd98b7102dd2f0e8b11d0f600bfb0c093", header.0.nonce);
+    assert_eq!(Some(NonceCount(1)), header.0.nonce_count);
+    let original_client_nonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ".to_owned();
+    assert!(header.0.client_nonce != Some(original_client_nonce))
+}
+
+#[test]
+fn test_authorization_with_userhash() {
+    let mut client = rfc7616_3_9_2_client();
+    let header = client.authorization(Method::Get, "/doe.json", b"")
+        .expect("Could not generate digest");
+    let userhash = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b";
+    assert_eq!(Username::Plain(userhash.to_owned()), header.0.username);
+    assert!(header.0.userhash);
+    assert_eq!("UTF-8", header.0.charset.expect("No charset found").to_string());
+    assert_eq!("3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5",
+               header.0.response)
+}
+
+#[test]
+fn test_authorization_with_encoded_username() {
+    let mut client = rfc7616_3_9_2_client();
+    let mut challenge = client.challenge().clone();
+    challenge.userhash = false;
+    client.set_challenge(challenge);
+    let header = client.authorization(Method::Get, "/doe.json", b"")
+        .expect("Could not generate digest");
+    assert_eq!("UTF-8''J%C3%A4s%C3%B8n%20Doe", header.0.username.to_string());
+    assert!(!header.0.userhash);
+    assert_eq!("3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5",
+               header.0.response)
+}
+
+#[test]
+fn test_authorization_with_precis() {
+    let mut client = rfc7616_3_9_2_client();
+    client.username = "Ja\u{308}s\u{f8}n Doe".to_owned();
+    client.use_precis(Profile::UsernameCasePreserved);
+    let header = client.authorization(Method::Get, "/doe.json", b"")
+        .expect("Could not generate digest");
+    let userhash = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b";
+    assert_eq!(Username::Plain(userhash.to_owned()), header.0.username);
+    assert_eq!("3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5",
+               header.0.response)
+}
+
+#[test]
+fn test_authorization_with_precis_and_disallowed_username() {
+    let mut client = rfc7616_3_9_2_client();
+    client.username = "J\u{e4}s\u{f8}n\tDoe".to_owned();
+    client.use_precis(Profile::UsernameCasePreserved);
+    assert_eq!(Err(Error::DisallowedCharacter('\t')),
+               client.authorization(Method::Get, "/doe.json", b"").map(|_| ()))
+}
+
+#[test]
+fn test_authorization_with_userhash_and_unknown_algorithm() {
+    let mut client = rfc7616_3_9_2_client();
+    let mut challenge = client.challenge().clone();
+    challenge.algorithm = HashAlgorithm::Unknown("SHA3-256".to_owned());
+    client.set_challenge(challenge);
+    assert_eq!(Err(Error::UnsupportedAlgorithm("SHA3-256".to_owned())),
+               client.authorization(Method::Get, "/doe.json", b"").map(|_| ()))
+}
+
+#[test]
+fn test_generated_client_nonces_are_unique() {
+    let client_nonce = generate_client_nonce();
+    assert_eq!(32, client_nonce.len());
+    assert!(client_nonce != generate_client_nonce())
+}
+
+#[test]
+fn test_authorization_with_first_offered_qop() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let mut challenge = client.challenge().clone();
+    challenge.qop = vec![Qop::AuthInt, Qop::Auth];
+    client.set_challenge(challenge);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!(Some(Qop::AuthInt), header.0.qop)
+}
+
+#[test]
+fn test_authorization_with_preferred_qop() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let mut challenge = client.challenge().clone();
+    challenge.qop = vec![Qop::Auth, Qop::AuthInt];
+    client.set_challenge(challenge);
+    client.prefer_qop(Qop::AuthInt);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!(Some(Qop::AuthInt), header.0.qop)
+}
+
+#[test]
+fn test_authorization_with_preferred_qop_not_offered() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    client.prefer_qop(Qop::AuthInt);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!(Some(Qop::Auth), header.0.qop)
+}
+
+fn authentication_info(client: &DigestClient) -> AuthenticationInfo {
+    AuthenticationInfo {
+        digest: Some("9b712497bc9f91499fbcca1dfc5f09a5".to_owned()),
+        next_nonce: None,
+        qop: Some(Qop::Auth),
+        client_nonce: Some(client.client_nonce.clone()),
+        nonce_count: Some(NonceCount(1)),
+        extensions: AuthenticationInfo::new_extensions(),
+    }
+}
+
+#[test]
+fn test_verify_authentication_info() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    let info = authentication_info(&client);
+    assert!(client.verify_authentication_info(&header, &info, b""));
+    assert_eq!(1, client.nonce_count)
+}
+
+#[test]
+fn test_verify_authentication_info_with_next_nonce() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    let mut info = authentication_info(&client);
+    info.next_nonce = Some("fedcba".to_owned());
+    assert!(client.verify_authentication_info(&header, &info, b""));
+    assert_eq!("fedcba", client.challenge().nonce);
+    let next_header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    assert_eq!("fedcba", next_header.0.nonce);
+    assert_eq!(Some(NonceCount(1)), next_header.0.nonce_count);
+    assert!(next_header.0.client_nonce != header.0.client_nonce)
+}
+
+#[test]
+fn test_verify_authentication_info_with_invalid_rspauth() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    let mut info = authentication_info(&client);
+    info.digest = Some("8ca523f5e9506fed4657c9700eebdbec".to_owned());
+    info.next_nonce = Some("fedcba".to_owned());
+    assert!(!client.verify_authentication_info(&header, &info, b""));
+    assert_eq!("7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", client.challenge().nonce)
+}
+
+#[test]
+fn test_verify_authentication_info_with_mismatched_nonce_count() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    let mut info = authentication_info(&client);
+    info.nonce_count = Some(NonceCount(2));
+    assert!(!client.verify_authentication_info(&header, &info, b""))
+}
+
+#[test]
+fn test_verify_authentication_info_with_mismatched_client_nonce() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let header = client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest");
+    let mut info = authentication_info(&client);
+    info.client_nonce = Some("0a4f113b".to_owned());
+    assert!(!client.verify_authentication_info(&header, &info, b""))
+}
+
+#[test]
+fn test_authorization_with_entity_hash() {
+    let mut client = rfc7616_3_9_1_client(HashAlgorithm::MD5);
+    let mut challenge = client.challenge().clone();
+    challenge.qop = vec![Qop::AuthInt];
+    client.set_challenge(challenge);
+    client.client_nonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ".to_owned();
+    let mut hasher = EntityBodyHasher::new(&client.challenge().algorithm)
+        .expect("Could not create hasher");
+    hasher.update(b"foo=bar");
+    let streamed = client.authorization_with_entity_hash(Method::Post,
+                                                        "/dir/index.html",
+                                                        &hasher.hex_digest())
+        .expect("Could not generate digest");
+    client.nonce_count = 0;
+    let buffered = client.authorization(Method::Post, "/dir/index.html", b"foo=bar")
+        .expect("Could not generate digest");
+    assert_eq!(buffered.0.response, streamed.0.response);
+    let password = "Circle of Life".to_owned();
+    assert!(streamed.0.validate_using_password(Method::Post, b"foo=bar", password).is_ok())
+}
+
+#[test]
+fn test_debug_omits_password() {
+    let debug = format!("{:?}", rfc7616_3_9_1_client(HashAlgorithm::MD5));
+    assert!(debug.contains("Mufasa"));
+    assert!(!debug.contains("Circle of Life"))
+}
@@ -0,0 +1,46 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! Comparisons of secret-dependent values that take the same amount of time regardless of where
+//! the values differ, so that they do not leak timing information.
+
+mod test;
+
+/// Whether two byte strings are equal. Every byte is compared, even after a mismatch is found.
+///
+/// Only the lengths are compared in variable time, which is fine for values like hexadecimal
+/// digests, whose length is not secret.
+pub fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    difference(a.iter().cloned().zip(b.iter().cloned())) == 0
+}
+
+/// Whether two strings are equal, compared as bytes in constant time.
+pub fn str_eq(a: &str, b: &str) -> bool {
+    eq(a.as_bytes(), b.as_bytes())
+}
+
+/// Accumulates the differing bits of every pair of bytes. Zero means that every pair is equal.
+fn difference<I: Iterator<Item = (u8, u8)>>(pairs: I) -> u8 {
+    pairs.fold(0, |difference, (a, b)| difference | (a ^ b))
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use std::cell::Cell;
+use super::{difference, eq, str_eq};
+
+/// Counts how many pairs of bytes have been compared.
+struct CountingPairs<'a, I> {
+    pairs: I,
+    count: &'a Cell<usize>,
+}
+
+impl<'a, I: Iterator<Item = (u8, u8)>> Iterator for CountingPairs<'a, I> {
+    type Item = (u8, u8);
+
+    fn next(&mut self) -> Option<(u8, u8)> {
+        let pair = self.pairs.next();
+        if pair.is_some() {
+            self.count.set(self.count.get() + 1);
+        }
+        pair
+    }
+}
+
+fn compared_pairs(a: &[u8], b: &[u8]) -> usize {
+    let count = Cell::new(0);
+    difference(CountingPairs {
+        pairs: a.iter().cloned().zip(b.iter().cloned()),
+        count: &count,
+    });
+    count.get()
+}
+
+#[test]
+fn test_eq() {
+    assert!(eq(b"6629fae49393a05397450978507c4ef1", b"6629fae49393a05397450978507c4ef1"));
+    assert!(eq(b"", b""));
+}
+
+#[test]
+fn test_eq_with_mismatch() {
+    assert!(!eq(b"6629fae49393a05397450978507c4ef1", b"7629fae49393a05397450978507c4ef1"));
+    assert!(!eq(b"6629fae49393a05397450978507c4ef1", b"6629fae49393a05397450978507c4ef2"));
+}
+
+#[test]
+fn test_eq_with_different_lengths() {
+    assert!(!eq(b"6629fae4", b"6629fae49393a053"));
+    assert!(!eq(b"", b"6629fae4"));
+    assert!(!eq(b"6629fae49393a05397450978507c4ef1", b"6629fae49393a05397450978507c4ef100"));
+}
+
+#[test]
+fn test_str_eq() {
+    assert!(str_eq("J\u{e4}s\u{f8}n Doe", "J\u{e4}s\u{f8}n Doe"));
+    assert!(!str_eq("J\u{e4}s\u{f8}n Doe", "Jason Doe"));
+}
+
+#[test]
+fn test_comparison_does_not_short_circuit() {
+    let expected = b"6629fae49393a05397450978507c4ef1";
+    assert_eq!(expected.len(), compared_pairs(expected, expected));
+    assert_eq!(expected.len(),
+               compared_pairs(expected, b"7629fae49393a05397450978507c4ef1"));
+    assert_eq!(expected.len(),
+               compared_pairs(expected, b"6629fae49393a05397450978507c4ef0"));
+}
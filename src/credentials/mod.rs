@@ -0,0 +1,210 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! Lookup of user credentials, for validating `Digest` headers without having the user's password
+//! or hashed A1 at hand.
+//!
+//! Implement `CredentialStore` to look up credentials in, for example, a database. This module
+//! provides an in-memory store and a store that is backed by a closure; `htdigest` files can also
+//! be used as stores.
+
+use digest::Digest;
+use std::collections::HashMap;
+use super::types::HashAlgorithm;
+use unicode_normalization::UnicodeNormalization;
+
+mod test;
+
+/// The secret that a `CredentialStore` has for a user.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Credential {
+    /// The user's plaintext password, which can be used with any algorithm.
+    Password(String),
+    /// The simple hashed A1 (see `Digest::simple_hashed_a1`) for the hash function of the
+    /// requested algorithm.
+    HashedA1(String),
+}
+
+/// Generates the userhash of a user, for stores that keep an index of userhashes. The username is
+/// normalized to Unicode Normalization Form C, as `Digest` does when validating the userhash of a
+/// header whose `charset` is `UTF-8`.
+///
+/// Returns `None` if the algorithm is unknown.
+pub fn userhash(username: &str, realm: &str, algorithm: &HashAlgorithm) -> Option<String> {
+    let username: String = username.nfc().collect();
+    Digest::userhash(&algorithm.hash_function(),
+                     username.into_bytes(),
+                     realm.to_owned())
+        .ok()
+}
+
+/// Looks up the credentials of users, either by username or by userhash.
+pub trait CredentialStore {
+    /// Looks up the secret of a user in a realm. `algorithm` is the algorithm that the client
+    /// used, for stores that keep a hashed A1 per hash function. Returns `None` if the user is
+    /// unknown, or if there is no secret for the algorithm.
+    fn credential(&self,
+                  username: &str,
+                  realm: &str,
+                  algorithm: &HashAlgorithm)
+                  -> Option<Credential>;
+
+    /// Looks up the username that a userhash (as defined in
+    /// [RFC 7616, section 3.4.4](https://tools.ietf.org/html/rfc7616#section-3.4.4)) was generated
+    /// from. Returns `None` if no user in the realm has the userhash.
+    ///
+    /// Userhashes are hexadecimal, so stores should match them case-insensitively, as
+    /// `Digest::validate_userhash` does. Stores should keep an index of userhashes instead of
+    /// hashing every username. The default implementation does not support userhashes, and always
+    /// returns `None`.
+    #[allow(unused_variables)]
+    fn username_for_userhash(&self,
+                             userhash: &str,
+                             realm: &str,
+                             algorithm: &HashAlgorithm)
+                             -> Option<String> {
+        None
+    }
+}
+
+/// A `CredentialStore` that keeps its credentials in memory.
+///
+/// Userhashes are indexed for every built-in hash function when a user is added. Userhashes of
+/// custom algorithms are looked up by hashing the usernames of the realm.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryCredentialStore {
+    passwords: HashMap<(String, String), String>,
+    hashed_a1s: HashMap<(String, String, String), String>,
+    userhashes: HashMap<(String, String, String), String>,
+}
+
+impl InMemoryCredentialStore {
+    /// Creates an empty store.
+    pub fn new() -> InMemoryCredentialStore {
+        InMemoryCredentialStore::default()
+    }
+
+    /// Adds a user with a plaintext password, replacing any previous password of the user in the
+    /// realm.
+    pub fn insert_password(&mut self, username: &str, realm: &str, password: &str) {
+        self.passwords.insert((username.to_owned(), realm.to_owned()), password.to_owned());
+        self.index_userhashes(username, realm);
+    }
+
+    /// Adds a user with the simple hashed A1 for the hash function of `algorithm`, replacing any
+    /// previous hashed A1 for that hash function.
+    pub fn insert_hashed_a1(&mut self,
+                            username: &str,
+                            realm: &str,
+                            algorithm: &HashAlgorithm,
+                            hashed_a1: &str) {
+        let key = (username.to_owned(),
+                   realm.to_owned(),
+                   algorithm.hash_function().to_string());
+        self.hashed_a1s.insert(key, hashed_a1.to_owned());
+        self.index_userhashes(username, realm);
+    }
+
+    fn index_userhashes(&mut self, username: &str, realm: &str) {
+        for algorithm in &[HashAlgorithm::MD5, HashAlgorithm::SHA256, HashAlgorithm::SHA512256] {
+            if let Some(userhash) = userhash(username, realm, algorithm) {
+                let key = (realm.to_owned(), algorithm.to_string(), userhash);
+                self.userhashes.insert(key, username.to_owned());
+            }
+        }
+    }
+
+    fn is_indexed(hash_function: &HashAlgorithm) -> bool {
+        match *hash_function {
+            HashAlgorithm::MD5 |
+            HashAlgorithm::SHA256 |
+            HashAlgorithm::SHA512256 => true,
+            _ => false,
+        }
+    }
+
+    /// The usernames of the realm, which may contain duplicates.
+    #[allow(bare_trait_objects)]
+    fn usernames<'a>(&'a self, realm: &'a str) -> Box<Iterator<Item = &'a String> + 'a> {
+        let passwords = self.passwords.keys().filter(move |key| key.1 == realm).map(|key| &key.0);
+        let hashed_a1s = self.hashed_a1s.keys().filter(move |key| key.1 == realm).map(|key| &key.0);
+        Box::new(passwords.chain(hashed_a1s))
+    }
+}
+
+impl CredentialStore for InMemoryCredentialStore {
+    fn credential(&self,
+                  username: &str,
+                  realm: &str,
+                  algorithm: &HashAlgorithm)
+                  -> Option<Credential> {
+        let key = (username.to_owned(), realm.to_owned());
+        if let Some(password) = self.passwords.get(&key) {
+            return Some(Credential::Password(password.clone()));
+        }
+        let key = (key.0, key.1, algorithm.hash_function().to_string());
+        self.hashed_a1s.get(&key).map(|hashed_a1| Credential::HashedA1(hashed_a1.clone()))
+    }
+
+    fn username_for_userhash(&self,
+                             userhash: &str,
+                             realm: &str,
+                             algorithm: &HashAlgorithm)
+                             -> Option<String> {
+        let hash_function = algorithm.hash_function();
+        let expected = userhash.to_lowercase();
+        if InMemoryCredentialStore::is_indexed(&hash_function) {
+            let key = (realm.to_owned(), hash_function.to_string(), expected);
+            return self.userhashes.get(&key).cloned();
+        }
+        let found = self.usernames(realm).find(|username| {
+            self::userhash(username, realm, &hash_function).as_ref() == Some(&expected)
+        });
+        found.cloned()
+    }
+}
+
+/// A `CredentialStore` that looks up credentials via a closure, which is given the username,
+/// realm and algorithm. Userhashes are not supported.
+pub struct FnCredentialStore<F> {
+    lookup: F,
+}
+
+impl<F> FnCredentialStore<F>
+    where F: Fn(&str, &str, &HashAlgorithm) -> Option<Credential>
+{
+    /// Creates a store, given the closure that looks up credentials.
+    pub fn new(lookup: F) -> FnCredentialStore<F> {
+        FnCredentialStore { lookup: lookup }
+    }
+}
+
+impl<F> CredentialStore for FnCredentialStore<F>
+    where F: Fn(&str, &str, &HashAlgorithm) -> Option<Credential>
+{
+    fn credential(&self,
+                  username: &str,
+                  realm: &str,
+                  algorithm: &HashAlgorithm)
+                  -> Option<Credential> {
+        (self.lookup)(username, realm, algorithm)
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use client::DigestClient;
+use digest::{Digest, Username};
+use digest::test_helper::rfc2617_digest_header;
+use error::ValidationError;
+use htdigest::Htdigest;
+use hyper::method::Method;
+use std::str::FromStr;
+use super::{Credential, CredentialStore, FnCredentialStore, InMemoryCredentialStore};
+use super::super::types::{HashAlgorithm, Qop};
+use www_authenticate::WwwAuthenticate;
+
+const REALM: &'static str = "testrealm@host.com";
+const PASSWORD: &'static str = "Circle Of Life";
+const HASHED_A1: &'static str = "939e7578ed9e3c518a452acee763bce9";
+
+fn userhash_digest(algorithm: HashAlgorithm, password: &str) -> Digest {
+    let challenge = WwwAuthenticate {
+        realm: REALM.to_owned(),
+        domain: vec![],
+        nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_owned(),
+        opaque: None,
+        stale: false,
+        algorithm: algorithm,
+        qop: vec![Qop::Auth],
+        charset: None,
+        userhash: true,
+        extensions: WwwAuthenticate::new_extensions(),
+    };
+    let mut client = DigestClient::new(challenge, "Mufasa".to_owned(), password.to_owned());
+    client.authorization(Method::Get, "/dir/index.html", b"")
+        .expect("Could not generate digest")
+        .0
+}
+
+fn password_store() -> InMemoryCredentialStore {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_password("Mufasa", REALM, PASSWORD);
+    store
+}
+
+#[test]
+fn test_in_memory_credential() {
+    let store = password_store();
+    assert_eq!(Some(Credential::Password(PASSWORD.to_owned())),
+               store.credential("Mufasa", REALM, &HashAlgorithm::SHA256));
+    assert_eq!(None, store.credential("Simba", REALM, &HashAlgorithm::SHA256));
+    assert_eq!(None, store.credential("Mufasa", "other realm", &HashAlgorithm::SHA256))
+}
+
+#[test]
+fn test_in_memory_hashed_a1_per_hash_function() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_hashed_a1("Mufasa", REALM, &HashAlgorithm::MD5, HASHED_A1);
+    assert_eq!(Some(Credential::HashedA1(HASHED_A1.to_owned())),
+               store.credential("Mufasa", REALM, &HashAlgorithm::MD5Session));
+    assert_eq!(None, store.credential("Mufasa", REALM, &HashAlgorithm::SHA256))
+}
+
+#[test]
+fn test_in_memory_username_for_userhash() {
+    let store = password_store();
+    let userhash = Digest::userhash(&HashAlgorithm::SHA256,
+                                    b"Mufasa".to_vec(),
+                                    REALM.to_owned())
+        .expect("Could not generate userhash");
+    assert_eq!(Some("Mufasa".to_owned()),
+               store.username_for_userhash(&userhash, REALM, &HashAlgorithm::SHA256));
+    assert_eq!(Some("Mufasa".to_owned()),
+               store.username_for_userhash(&userhash.to_uppercase(),
+                                           REALM,
+                                           &HashAlgorithm::SHA256));
+    assert_eq!(None,
+               store.username_for_userhash(&userhash, "other realm", &HashAlgorithm::SHA256));
+    assert_eq!(None,
+               store.username_for_userhash(&userhash, REALM, &HashAlgorithm::MD5))
+}
+
+#[test]
+fn test_in_memory_username_for_non_ascii_userhash() {
+    let decomposed = "Ja\u{308}s\u{f8}n Doe";
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_password(decomposed, "api@example.org", "Secret, or not?");
+    let userhash = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b";
+    assert_eq!(Some(decomposed.to_owned()),
+               store.username_for_userhash(userhash,
+                                           "api@example.org",
+                                           &HashAlgorithm::SHA512256Session));
+    // From RFC 7616
+    let digest = Digest::from_str("username=\"793263caabb707a56211940d90411ea4a575adeccb7e360aeb6\
+                                   24ed06ece9b0b\", realm=\"api@example.org\", \
+                                   uri=\"/doe.json\", algorithm=SHA-512-256, \
+                                   nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+                                   nc=00000001, \
+                                   cnonce=\"NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v\", \
+                                   qop=auth, \
+                                   response=\"3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9\
+                                   d95eeb1cec68a5\", \
+                                   opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
+                                   charset=UTF-8, userhash=true")
+        .expect("Could not parse digest");
+    assert_eq!(Ok(()), digest.validate_using_credential_store(Method::Get, b"", &store));
+}
+
+#[test]
+fn test_validate_using_in_memory_password() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Ok(()),
+               digest.validate_using_credential_store(Method::Get, b"", &password_store()));
+}
+
+#[test]
+fn test_validate_using_in_memory_hashed_a1() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_hashed_a1("Mufasa", REALM, &HashAlgorithm::MD5, HASHED_A1);
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Ok(()), digest.validate_using_credential_store(Method::Get, b"", &store));
+}
+
+#[test]
+fn test_validate_using_unknown_user() {
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.username = Username::Plain("Simba".to_owned());
+    assert_eq!(Err(ValidationError::UnknownUser),
+               digest.validate_using_credential_store(Method::Get, b"", &password_store()));
+}
+
+#[test]
+fn test_validate_using_wrong_password() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_password("Mufasa", REALM, "Circle of Death");
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Err(ValidationError::WrongCredentials),
+               digest.validate_using_credential_store(Method::Get, b"", &store));
+}
+
+#[test]
+fn test_validate_using_userhash() {
+    let digest = userhash_digest(HashAlgorithm::SHA256, PASSWORD);
+    assert!(digest.userhash);
+    assert_eq!(Ok(()),
+               digest.validate_using_credential_store(Method::Get, b"", &password_store()));
+}
+
+#[test]
+fn test_validate_using_unknown_userhash() {
+    let mut digest = userhash_digest(HashAlgorithm::SHA256, PASSWORD);
+    digest.username = Username::Plain("0123456789abcdef0123456789abcdef".to_owned());
+    assert_eq!(Err(ValidationError::UnknownUser),
+               digest.validate_using_credential_store(Method::Get, b"", &password_store()));
+}
+
+#[test]
+fn test_validate_using_htdigest() {
+    let htdigest = Htdigest::from_str(&format!("Mufasa:{}:{}", REALM, HASHED_A1))
+        .expect("Could not parse password file");
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Ok(()), digest.validate_using_credential_store(Method::Get, b"", &htdigest));
+    let digest = userhash_digest(HashAlgorithm::MD5Session, PASSWORD);
+    assert_eq!(Ok(()), digest.validate_using_credential_store(Method::Get, b"", &htdigest));
+    let digest = userhash_digest(HashAlgorithm::SHA256, PASSWORD);
+    assert_eq!(Err(ValidationError::UnknownUser),
+               digest.validate_using_credential_store(Method::Get, b"", &htdigest));
+}
+
+#[test]
+fn test_validate_using_closure() {
+    let store = FnCredentialStore::new(|username: &str, realm: &str, _: &HashAlgorithm| {
+        if username == "Mufasa" && realm == REALM {
+            Some(Credential::Password(PASSWORD.to_owned()))
+        } else {
+            None
+        }
+    });
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Ok(()), digest.validate_using_credential_store(Method::Get, b"", &store));
+    let digest = userhash_digest(HashAlgorithm::MD5, PASSWORD);
+    assert_eq!(Err(ValidationError::UnknownUser),
+               digest.validate_using_credential_store(Method::Get, b"", &store));
+}
@@ -20,19 +20,30 @@
 
 //! An HTTP Digest implementation for [Hyper](http://hyper.rs)'s `Authorization` header.
 
-use hyper::error::Error;
+use authentication_info::AuthenticationInfo;
+use constant_time;
+use credentials::{Credential, CredentialStore};
+use error::{Error, ValidationError};
 use hyper::header::{Charset, Scheme};
 use hyper::header::parsing::{ExtendedValue, parse_extended_value};
 use hyper::method::Method;
-use parsing::{append_parameter, parse_parameters, unraveled_map_value};
+use hyper::uri::RequestUri;
+use nonce::{NonceContext, NonceManager, NonceStatus};
+use parsing::{append_extensions, append_parameter, is_utf8, parameter_value, parse_charset,
+              parse_parameters, Declarations, Decoding};
+use precis::Profile;
+use replay::ReplayGuard;
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
-use super::types::{HashAlgorithm, NonceCount, Qop};
+use super::types::{EntityBodyHasher, ExtensionParameters, HashAlgorithm, NonceCount, Qop};
 use unicase::UniCase;
+use unicode_normalization::UnicodeNormalization;
+use uri;
 
 mod test;
-mod test_helper;
+#[cfg(test)]
+pub mod test_helper;
 
 /// Represents a `username` (or user hash, if the header's `userhash` parameter is `true`).
 #[derive(Clone, Debug, PartialEq)]
@@ -44,6 +55,24 @@ pub enum Username {
     Encoded(ExtendedValue),
 }
 
+impl Username {
+    /// Normalizes the username to Unicode Normalization Form C, as the `UsernameCasePreserved`
+    /// profile of [RFC 7613, section 3.3](https://tools.ietf.org/html/rfc7613#section-3.3)
+    /// requires. Encoded usernames that are not valid UTF-8 are left unchanged.
+    fn nfc(self) -> Username {
+        match self {
+            Username::Plain(name) => Username::Plain(name.nfc().collect()),
+            Username::Encoded(mut encoded) => {
+                encoded.value = match String::from_utf8(encoded.value) {
+                    Ok(name) => name.nfc().collect::<String>().into_bytes(),
+                    Err(err) => err.into_bytes(),
+                };
+                Username::Encoded(encoded)
+            }
+        }
+    }
+}
+
 impl fmt::Display for Username {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -73,7 +102,8 @@ pub struct Digest {
     pub response: String,
     /// Either the absolute path or URI of the HTTP request, parameter name `uri`.
     pub request_uri: String,
-    /// The hash algorithm to use when generating the `response`.
+    /// The hash algorithm to use when generating the `response`. Algorithms that are not built in
+    /// must be resolved via `AlgorithmRegistry::resolve` before the `response` can be validated.
     pub algorithm: HashAlgorithm,
     /// Quality of protection. Optional only in RFC 2067 mode.
     pub qop: Option<Qop>,
@@ -85,6 +115,8 @@ pub struct Digest {
     pub charset: Option<Charset>,
     /// Whether `username` is a userhash. Added for RFC 7616.
     pub userhash: bool,
+    /// Extension parameters, which are not defined by RFC 7616.
+    pub extensions: ExtensionParameters,
 }
 
 impl Scheme for Digest {
@@ -128,38 +160,57 @@ impl Scheme for Digest {
         if self.userhash {
             append_parameter(&mut serialized, "userhash", "true", false);
         }
+        append_extensions(&mut serialized, &self.extensions);
         write!(f, "{}", serialized)
     }
 }
 
+/// The parameters of the `Digest` scheme, as described in
+/// [RFC 7616, section 3.4](https://tools.ietf.org/html/rfc7616#section-3.4).
+const PARAMETERS: &'static Declarations = &[("username", Decoding::QuotedString),
+                                             ("username*", Decoding::ExtValue),
+                                             ("realm", Decoding::QuotedString),
+                                             ("nonce", Decoding::QuotedString),
+                                             ("uri", Decoding::QuotedString),
+                                             ("response", Decoding::QuotedString),
+                                             ("algorithm", Decoding::Token),
+                                             ("cnonce", Decoding::QuotedString),
+                                             ("opaque", Decoding::QuotedString),
+                                             ("qop", Decoding::Token),
+                                             ("nc", Decoding::Token),
+                                             ("charset", Decoding::Token),
+                                             ("userhash", Decoding::Token)];
+
 fn parse_username(map: &HashMap<UniCase<String>, String>) -> Result<Username, Error> {
-    if let Some(value) = unraveled_map_value(map, "username") {
-        if unraveled_map_value(map, "username*").is_some() {
-            Err(Error::Header)
+    if let Some(value) = parameter_value(map, "username") {
+        if parameter_value(map, "username*").is_some() {
+            Err(Error::ConflictingParameters("username", "username*"))
         } else {
             Ok(Username::Plain(value))
         }
-    } else if let Some(encoded) = unraveled_map_value(map, "username*") {
-        if let Some(userhash) = unraveled_map_value(map, "userhash") {
+    } else if let Some(encoded) = parameter_value(map, "username*") {
+        if let Some(userhash) = parameter_value(map, "userhash") {
             if userhash == "true" {
-                return Err(Error::Header);
+                return Err(Error::ConflictingParameters("username*", "userhash"));
             }
         }
 
-        if let Ok(extended_value) = parse_extended_value(&encoded) {
-            Ok(Username::Encoded(extended_value))
-        } else {
-            Err(Error::Header)
+        match parse_extended_value(&encoded) {
+            Ok(ref extended_value) if !is_utf8(&extended_value.charset) => {
+                Err(Error::UnsupportedCharset(extended_value.charset.to_string()))
+            }
+            Ok(extended_value) => Ok(Username::Encoded(extended_value)),
+            Err(_) => Err(Error::MalformedParameter("username*")),
         }
     } else {
-        Err(Error::Header)
+        Err(Error::MissingParameter("username"))
     }
 }
 
 impl FromStr for Digest {
     type Err = Error;
     fn from_str(s: &str) -> Result<Digest, Error> {
-        let param_map = parse_parameters(s);
+        let (param_map, extensions) = parse_parameters(s, PARAMETERS)?;
         let username: Username;
         let realm: String;
         let nonce: String;
@@ -172,50 +223,38 @@ impl FromStr for Digest {
             Ok(value) => username = value,
             Err(err) => return Err(err),
         }
-        match unraveled_map_value(&param_map, "realm") {
+        match parameter_value(&param_map, "realm") {
             Some(value) => realm = value,
-            None => return Err(Error::Header),
+            None => return Err(Error::MissingParameter("realm")),
         }
-        match unraveled_map_value(&param_map, "nonce") {
+        match parameter_value(&param_map, "nonce") {
             Some(value) => nonce = value,
-            None => return Err(Error::Header),
+            None => return Err(Error::MissingParameter("nonce")),
         }
         let nonce_count = match NonceCount::from_parameters(&param_map) {
             Ok(value) => value,
             Err(err) => return Err(err),
         };
-        match unraveled_map_value(&param_map, "response") {
+        match parameter_value(&param_map, "response") {
             Some(value) => response = value,
-            None => return Err(Error::Header),
+            None => return Err(Error::MissingParameter("response")),
         }
-        match unraveled_map_value(&param_map, "uri") {
+        match parameter_value(&param_map, "uri") {
             Some(value) => request_uri = value,
-            None => return Err(Error::Header),
+            None => return Err(Error::MissingParameter("uri")),
         }
-        if let Some(value) = unraveled_map_value(&param_map, "algorithm") {
-            match HashAlgorithm::from_str(&value[..]) {
-                Ok(converted) => algorithm = converted,
-                Err(_) => return Err(Error::Header),
-            }
+        if let Some(value) = parameter_value(&param_map, "algorithm") {
+            algorithm = HashAlgorithm::from_str(&value[..])?;
         } else {
             algorithm = HashAlgorithm::MD5;
         }
         let qop = Qop::from_parameters(&param_map)?;
-        if let Some(value) = unraveled_map_value(&param_map, "charset") {
-            let utf8 = UniCase("utf-8".to_owned());
-            charset = if UniCase(value.clone()) == utf8 {
-                Some(Charset::Ext("UTF-8".to_owned()))
-            } else {
-                return Err(Error::Header);
-            }
-        } else {
-            charset = None;
-        }
-        if let Some(value) = unraveled_map_value(&param_map, "userhash") {
+        charset = parse_charset(&param_map)?;
+        if let Some(value) = parameter_value(&param_map, "userhash") {
             match &value[..] {
                 "true" => userhash = true,
                 "false" => userhash = false,
-                _ => return Err(Error::Header),
+                _ => return Err(Error::MalformedParameter("userhash")),
             }
         } else {
             userhash = false;
@@ -229,19 +268,30 @@ impl FromStr for Digest {
             request_uri: request_uri,
             algorithm: algorithm,
             qop: qop,
-            client_nonce: unraveled_map_value(&param_map, "cnonce"),
-            opaque: unraveled_map_value(&param_map, "opaque"),
+            client_nonce: parameter_value(&param_map, "cnonce"),
+            opaque: parameter_value(&param_map, "opaque"),
             charset: charset,
             userhash: userhash,
+            extensions: extensions,
         })
     }
 }
 
 impl Digest {
+    /// Creates an empty collection of extension parameters, in which the parameters of RFC 7616
+    /// are reserved.
+    pub fn new_extensions() -> ExtensionParameters {
+        ExtensionParameters::new(PARAMETERS)
+    }
+
     /// Generates a userhash, as defined in
     /// [RFC 7616, section 3.4.4](https://tools.ietf.org/html/rfc7616#section-3.4.4).
-    pub fn userhash(algorithm: &HashAlgorithm, username: Vec<u8>, realm: String) -> String {
-        let mut to_hash = username.clone();
+    /// Returns an error if the algorithm is unknown.
+    pub fn userhash(algorithm: &HashAlgorithm,
+                    username: Vec<u8>,
+                    realm: String)
+                    -> Result<String, Error> {
+        let mut to_hash = username;
         to_hash.push(b':');
         to_hash.append(&mut realm.into_bytes());
         algorithm.hex_digest(to_hash)
@@ -249,26 +299,84 @@ impl Digest {
 
     /// Validates a userhash (as defined in
     /// [RFC 7616, section 3.4.4](https://tools.ietf.org/html/rfc7616#section-3.4.4)), given a
-    /// `Digest` header.
+    /// `Digest` header. The userhash is hexadecimal, so it is compared case-insensitively.
     ///
-    /// If userhash is `false`, returns `false`.
+    /// If userhash is `false`, or the algorithm is unknown, returns `false`.
     pub fn validate_userhash(&self, username: Username) -> bool {
         match self.username {
             Username::Plain(ref userhash) => {
-                let name = match username {
+                let name = match self.prepare_username(username) {
                     Username::Plain(value) => value.into_bytes(),
                     Username::Encoded(encoded) => encoded.value,
                 };
-                *userhash == Digest::userhash(&self.algorithm, name, self.realm.clone())
+                match Digest::userhash(&self.algorithm, name, self.realm.clone()) {
+                    Ok(expected) => constant_time::str_eq(&userhash.to_lowercase(), &expected),
+                    Err(_) => false,
+                }
             }
             Username::Encoded(_) => false,
         }
     }
 
+    /// Whether the credentials are encoded in UTF-8, per the `charset` parameter.
+    fn is_utf8(&self) -> bool {
+        self.charset.as_ref().map_or(false, is_utf8)
+    }
+
+    /// Prepares the username for the A1 value or the userhash. When `charset` is `UTF-8`, it is
+    /// normalized to Unicode Normalization Form C.
+    fn prepare_username(&self, username: Username) -> Username {
+        if self.is_utf8() {
+            username.nfc()
+        } else {
+            username
+        }
+    }
+
+    /// Prepares the password for the A1 value. When `charset` is `UTF-8`, it is normalized to
+    /// Unicode Normalization Form C, as the `OpaqueString` profile of
+    /// [RFC 7613, section 4.2](https://tools.ietf.org/html/rfc7613#section-4.2) requires.
+    fn prepare_password(&self, password: String) -> String {
+        if self.is_utf8() {
+            password.nfc().collect()
+        } else {
+            password
+        }
+    }
+
+    /// Prepares a username and password with the PRECIS profiles of
+    /// [RFC 7613](https://tools.ietf.org/html/rfc7613), as recommended by
+    /// [RFC 7616, section 4](https://tools.ietf.org/html/rfc7616#section-4). The username is
+    /// prepared with `profile`, and the password with `Profile::OpaqueString`.
+    ///
+    /// This is opt-in: pass the prepared credentials to any of the methods that take a username
+    /// or password. They are only prepared when `charset` is `UTF-8`, otherwise they are
+    /// returned unchanged.
+    pub fn prepare_credentials(&self,
+                               profile: Profile,
+                               username: Username,
+                               password: String)
+                               -> Result<(Username, String), Error> {
+        if !self.is_utf8() {
+            return Ok((username, password));
+        }
+        let username = match username {
+            Username::Plain(name) => Username::Plain(profile.enforce(&name)?),
+            Username::Encoded(mut encoded) => {
+                encoded.value = match String::from_utf8(encoded.value) {
+                    Ok(name) => profile.enforce(&name)?.into_bytes(),
+                    Err(_) => return Err(Error::MalformedParameter("username*")),
+                };
+                Username::Encoded(encoded)
+            }
+        };
+        Ok((username, Profile::OpaqueString.enforce(&password)?))
+    }
+
     fn simple_a1(username: Username, realm: String, password: String) -> Vec<u8> {
         let mut a1: Vec<u8> = match username {
-            Username::Plain(name) => name.clone().into_bytes(),
-            Username::Encoded(encoded) => encoded.value.clone(),
+            Username::Plain(name) => name.into_bytes(),
+            Username::Encoded(encoded) => encoded.value,
         };
         a1.push(b':');
         a1.append(&mut realm.into_bytes());
@@ -286,38 +394,65 @@ impl Digest {
     /// To see how a simple A1 value is constructed, see
     /// [RFC 7616, section 3.4.2](https://tools.ietf.org/html/rfc7616#section-3.4.2).
     /// This is the definition when the algorithm is "unspecified".
+    ///
+    /// Returns an error if the algorithm is unknown.
     pub fn simple_hashed_a1(algorithm: &HashAlgorithm,
                             username: Username,
                             realm: String,
                             password: String)
-                            -> String {
+                            -> Result<String, Error> {
         algorithm.hex_digest(Digest::simple_a1(username, realm, password))
     }
 
     // RFC 7616, Section 3.4.2
     fn a1(&self, username: Username, password: String) -> Result<Vec<u8>, Error> {
+        let username = self.prepare_username(username);
+        let password = self.prepare_password(password);
         let realm = self.realm.clone();
+        if self.algorithm.is_session() {
+            let simple_hashed_a1 = self.algorithm
+                .hex_digest(Digest::simple_a1(username, realm, password))?;
+            self.session_a1(simple_hashed_a1)
+        } else {
+            Ok(Digest::simple_a1(username, realm, password))
+        }
+    }
+
+    // RFC 7616, Section 3.4.2, for the -sess algorithms
+    fn session_a1(&self, simple_hashed_a1: String) -> Result<Vec<u8>, Error> {
+        if let Some(ref client_nonce) = self.client_nonce {
+            let mut a1 = simple_hashed_a1.into_bytes();
+            a1.push(b':');
+            a1.append(&mut self.nonce.clone().into_bytes());
+            a1.push(b':');
+            a1.append(&mut client_nonce.clone().into_bytes());
+            Ok(a1)
+        } else {
+            Err(Error::MissingClientNonce)
+        }
+    }
+
+    /// Generates the hexadecimal digest of the A1 value, given the simple hashed A1 (as generated
+    /// by `simple_hashed_a1`, or as stored in an `htdigest` file). The result can be passed to
+    /// `validate_using_hashed_a1`.
+    ///
+    /// For the `-sess` algorithms, the nonce and client nonce are hashed with the simple hashed
+    /// A1. Otherwise, the simple hashed A1 is returned unchanged.
+    pub fn hashed_a1_from_simple(&self, simple_hashed_a1: String) -> Result<String, Error> {
+        self.supported_algorithm()?;
+        if self.algorithm.is_session() {
+            let a1 = self.session_a1(simple_hashed_a1)?;
+            self.algorithm.hex_digest(a1)
+        } else {
+            Ok(simple_hashed_a1)
+        }
+    }
+
+    /// Digests cannot be generated with an unknown algorithm.
+    fn supported_algorithm(&self) -> Result<(), Error> {
         match self.algorithm {
-            HashAlgorithm::MD5 |
-            HashAlgorithm::SHA256 |
-            HashAlgorithm::SHA512256 => Ok(Digest::simple_a1(username, realm, password)),
-
-            HashAlgorithm::MD5Session |
-            HashAlgorithm::SHA256Session |
-            HashAlgorithm::SHA512256Session => {
-                if let Some(ref client_nonce) = self.client_nonce {
-                    let simple_hashed_a1 = self.algorithm
-                        .hex_digest(Digest::simple_a1(username, realm, password));
-                    let mut a1 = simple_hashed_a1.into_bytes();
-                    a1.push(b':');
-                    a1.append(&mut self.nonce.clone().into_bytes());
-                    a1.push(b':');
-                    a1.append(&mut client_nonce.clone().into_bytes());
-                    Ok(a1)
-                } else {
-                    Err(Error::Header)
-                }
-            }
+            HashAlgorithm::Unknown(ref name) => Err(Error::UnsupportedAlgorithm(name.clone())),
+            _ => Ok(()),
         }
     }
 
@@ -326,46 +461,67 @@ impl Digest {
     /// To see how an A1 value is constructed, see
     /// [RFC 7616, section 3.4.2](https://tools.ietf.org/html/rfc7616#section-3.4.2).
     fn hashed_a1(&self, username: Username, password: String) -> Result<String, Error> {
-        if let Ok(a1) = self.a1(username, password) {
-            Ok(self.algorithm.hex_digest(a1))
-        } else {
-            Err(Error::Header)
-        }
+        let a1 = self.a1(username, password)?;
+        self.algorithm.hex_digest(a1)
     }
 
-    // RFC 7616, Section 3.4.3
-    fn a2(&self, method: Method, entity_body: String) -> String {
+    /// The hash of the entity body, which is only needed when `qop` is `auth-int`.
+    fn entity_hash(&self, entity_body: &[u8]) -> Result<String, Error> {
         match self.qop {
             Some(Qop::AuthInt) => {
-                format!("{}:{}:{}",
-                        method,
-                        self.request_uri,
-                        self.algorithm.hex_digest(entity_body.into_bytes()))
+                let mut hasher = EntityBodyHasher::new(&self.algorithm)?;
+                hasher.update(entity_body);
+                Ok(hasher.hex_digest())
             }
+            _ => Ok(String::new()),
+        }
+    }
+
+    // RFC 7616, Section 3.4.3. For `rspauth`, the method is empty (RFC 7616, Section 3.5).
+    fn a2(&self, method: &str, entity_hash: &str) -> String {
+        match self.qop {
+            Some(Qop::AuthInt) => format!("{}:{}:{}", method, self.request_uri, entity_hash),
             _ => format!("{}:{}", method, self.request_uri),
         }
     }
 
-    fn hashed_a2(&self, method: Method, entity_body: String) -> String {
-        self.algorithm.hex_digest(self.a2(method, entity_body).into_bytes())
+    fn hashed_a2(&self, method: Method, entity_hash: &str) -> Result<String, Error> {
+        let a2 = self.a2(&method.to_string(), entity_hash);
+        self.algorithm.hex_digest(a2.into_bytes())
     }
 
-    fn kd(algorithm: &HashAlgorithm, secret: String, data: String) -> String {
+    fn kd(algorithm: &HashAlgorithm, secret: String, data: String) -> Result<String, Error> {
         let value = format!("{}:{}", secret, data);
         algorithm.hex_digest(value.into_bytes())
     }
 
-    fn using_username_and_password(&self,
-                                   method: Method,
-                                   entity_body: String,
-                                   username: Username,
-                                   password: String)
-                                   -> Result<String, Error> {
-        if let Ok(a1) = self.hashed_a1(username, password) {
-            self.using_hashed_a1(method, entity_body, a1)
-        } else {
-            Err(Error::Header)
-        }
+    /// Generates a digest, given an HTTP request, a username, and a password.
+    ///
+    /// Unlike `using_password`, this does not use `Digest.username` to generate the A1 value, so it
+    /// can be used when `username` is a userhash.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn using_username_and_password(&self,
+                                       method: Method,
+                                       entity_body: &[u8],
+                                       username: Username,
+                                       password: String)
+                                       -> Result<String, Error> {
+        let entity_hash = self.entity_hash(entity_body)?;
+        self.using_username_password_and_entity_hash(method, &entity_hash, username, password)
+    }
+
+    /// Like `using_username_and_password`, but given the hexadecimal hash of the entity body
+    /// (generated via `EntityBodyHasher`) instead of the entity body itself.
+    pub fn using_username_password_and_entity_hash(&self,
+                                                   method: Method,
+                                                   entity_hash: &str,
+                                                   username: Username,
+                                                   password: String)
+                                                   -> Result<String, Error> {
+        let a1 = self.hashed_a1(username, password)?;
+        self.using_hashed_a1_and_entity_hash(method, entity_hash, a1)
     }
 
     /// Generates a digest, given an HTTP request and a password.
@@ -374,14 +530,22 @@ impl Digest {
     /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
     pub fn using_password(&self,
                           method: Method,
-                          entity_body: String,
+                          entity_body: &[u8],
                           password: String)
                           -> Result<String, Error> {
-        if let Ok(a1) = self.hashed_a1(self.username.clone(), password) {
-            self.using_hashed_a1(method, entity_body, a1)
-        } else {
-            Err(Error::Header)
-        }
+        let entity_hash = self.entity_hash(entity_body)?;
+        self.using_password_and_entity_hash(method, &entity_hash, password)
+    }
+
+    /// Like `using_password`, but given the hexadecimal hash of the entity body (generated via
+    /// `EntityBodyHasher`) instead of the entity body itself.
+    pub fn using_password_and_entity_hash(&self,
+                                          method: Method,
+                                          entity_hash: &str,
+                                          password: String)
+                                          -> Result<String, Error> {
+        let a1 = self.hashed_a1(self.username.clone(), password)?;
+        self.using_hashed_a1_and_entity_hash(method, entity_hash, a1)
     }
 
     /// Generates a digest, given an HTTP request and a hexadecimal digest of an A1 string.
@@ -393,16 +557,34 @@ impl Digest {
     /// generation.
     pub fn using_hashed_a1(&self,
                            method: Method,
-                           entity_body: String,
+                           entity_body: &[u8],
                            a1: String)
                            -> Result<String, Error> {
-        let a2 = self.hashed_a2(method, entity_body);
+        let entity_hash = self.entity_hash(entity_body)?;
+        self.using_hashed_a1_and_entity_hash(method, &entity_hash, a1)
+    }
+
+    /// Like `using_hashed_a1`, but given the hexadecimal hash of the entity body (generated via
+    /// `EntityBodyHasher`) instead of the entity body itself.
+    pub fn using_hashed_a1_and_entity_hash(&self,
+                                           method: Method,
+                                           entity_hash: &str,
+                                           a1: String)
+                                           -> Result<String, Error> {
+        let a2 = self.hashed_a2(method, entity_hash)?;
+        self.using_hashed_a1_and_a2(a1, a2)
+    }
+
+    fn using_hashed_a1_and_a2(&self, a1: String, a2: String) -> Result<String, Error> {
         let data: String;
         if let Some(ref qop) = self.qop {
             match *qop {
                 Qop::Auth | Qop::AuthInt => {
-                    if self.client_nonce.is_none() || self.nonce_count.is_none() {
-                        return Err(Error::Header);
+                    if self.client_nonce.is_none() {
+                        return Err(Error::MissingParameter("cnonce"));
+                    }
+                    if self.nonce_count.is_none() {
+                        return Err(Error::MissingParameter("nc"));
                     }
                     let nonce = self.nonce.clone();
                     let nonce_count = self.nonce_count.clone().expect("No nonce count found");
@@ -413,36 +595,142 @@ impl Digest {
         } else {
             data = format!("{}:{}", self.nonce, a2);
         }
-        Ok(Digest::kd(&self.algorithm, a1, data))
+        Digest::kd(&self.algorithm, a1, data)
+    }
+
+    /// Generates the `Authentication-Info` header for a validated request, given a password.
+    ///
+    /// The `rspauth` value is calculated like `response`, except that the method is omitted from
+    /// A2, as described in
+    /// [RFC 7616, section 3.5](https://tools.ietf.org/html/rfc7616#section-3.5).
+    /// `response_body` is only used when `qop` is `auth-int`, and should be empty if the response
+    /// has no body.
+    ///
+    /// Returns `Error::MissingUsername` if `userhash` is `true`, because A1 is generated from the
+    /// username instead of the userhash. Use `authentication_info_using_username_and_password`
+    /// with the username that the userhash was validated against instead.
+    pub fn authentication_info_using_password(&self,
+                                              response_body: &[u8],
+                                              password: String,
+                                              next_nonce: Option<String>)
+                                              -> Result<AuthenticationInfo, Error> {
+        if self.userhash {
+            return Err(Error::MissingUsername);
+        }
+        self.authentication_info_using_username_and_password(response_body,
+                                                             self.username.clone(),
+                                                             password,
+                                                             next_nonce)
+    }
+
+    /// Like `authentication_info_using_password`, but does not use `Digest.username` to generate
+    /// the A1 value, so it can be used when `username` is a userhash.
+    pub fn authentication_info_using_username_and_password(&self,
+                                                           response_body: &[u8],
+                                                           username: Username,
+                                                           password: String,
+                                                           next_nonce: Option<String>)
+                                                           -> Result<AuthenticationInfo, Error> {
+        let a1 = self.hashed_a1(username, password)?;
+        self.authentication_info_using_hashed_a1(response_body, a1, next_nonce)
+    }
+
+    /// Generates the `Authentication-Info` header for a validated request, given a hexadecimal
+    /// digest of an A1 string.
+    ///
+    /// This is intended to be used in applications that use the `htdigest` style of secret hash
+    /// generation.
+    pub fn authentication_info_using_hashed_a1(&self,
+                                               response_body: &[u8],
+                                               a1: String,
+                                               next_nonce: Option<String>)
+                                               -> Result<AuthenticationInfo, Error> {
+        let a2 = self.a2("", &self.entity_hash(response_body)?);
+        let a2 = self.algorithm.hex_digest(a2.into_bytes())?;
+        let rspauth = self.using_hashed_a1_and_a2(a1, a2)?;
+        Ok(AuthenticationInfo {
+            digest: Some(rspauth),
+            next_nonce: next_nonce,
+            qop: self.qop.clone(),
+            client_nonce: self.client_nonce.clone(),
+            nonce_count: self.nonce_count.clone(),
+            extensions: AuthenticationInfo::new_extensions(),
+        })
+    }
+
+    /// Validates an `Authentication-Info` header sent by the server in response to this `Digest`,
+    /// given the response body, a username, and a password.
+    ///
+    /// The `rspauth` value is recalculated, and `qop`, `cnonce` and `nc` must match the ones sent
+    /// in this `Digest`. `response_body` is only used when `qop` is `auth-int`.
+    pub fn validate_authentication_info(&self,
+                                        info: &AuthenticationInfo,
+                                        response_body: &[u8],
+                                        username: Username,
+                                        password: String)
+                                        -> bool {
+        if info.qop != self.qop || info.client_nonce != self.client_nonce ||
+           info.nonce_count != self.nonce_count {
+            return false;
+        }
+        let a1 = match self.hashed_a1(username, password) {
+            Ok(value) => value,
+            Err(_) => return false,
+        };
+        match self.authentication_info_using_hashed_a1(response_body, a1, None) {
+            Ok(expected) => {
+                match (expected.digest, info.digest.as_ref()) {
+                    (Some(ref expected), Some(actual)) => constant_time::str_eq(expected, actual),
+                    _ => false,
+                }
+            }
+            Err(_) => false,
+        }
     }
 
-    fn validate_using_username_and_password(&self,
-                                            method: Method,
-                                            entity_body: String,
-                                            username: Username,
-                                            password: String)
-                                            -> bool {
-        if let Ok(hex_digest) =
-               self.using_username_and_password(method, entity_body, username, password) {
-            hex_digest == self.response
+    fn validate_response(&self, hex_digest: String) -> Result<(), ValidationError> {
+        if constant_time::str_eq(&hex_digest, &self.response) {
+            Ok(())
         } else {
-            false
+            Err(ValidationError::WrongCredentials)
         }
     }
 
+    fn validate_using_username_password_and_entity_hash(&self,
+                                                        method: Method,
+                                                        entity_hash: &str,
+                                                        username: Username,
+                                                        password: String)
+                                                        -> Result<(), ValidationError> {
+        let hex_digest =
+            self.using_username_password_and_entity_hash(method, entity_hash, username, password)?;
+        self.validate_response(hex_digest)
+    }
+
     /// Validates a `Digest.response`, given an HTTP request and a password.
     ///
     /// `entity_body` is defined in
     /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
     pub fn validate_using_password(&self,
                                    method: Method,
-                                   entity_body: String,
+                                   entity_body: &[u8],
                                    password: String)
-                                   -> bool {
-        self.validate_using_username_and_password(method,
-                                                  entity_body,
-                                                  self.username.clone(),
-                                                  password)
+                                   -> Result<(), ValidationError> {
+        let entity_hash = self.entity_hash(entity_body)?;
+        self.validate_using_password_and_entity_hash(method, &entity_hash, password)
+    }
+
+    /// Like `validate_using_password`, but given the hexadecimal hash of the entity body
+    /// (generated via `EntityBodyHasher`) instead of the entity body itself.
+    pub fn validate_using_password_and_entity_hash(&self,
+                                                   method: Method,
+                                                   entity_hash: &str,
+                                                   password: String)
+                                                   -> Result<(), ValidationError> {
+        self.validate_using_username_password_and_entity_hash(method,
+                                                              entity_hash,
+                                                              self.username.clone(),
+                                                              password)
     }
 
     /// Validates a `Digest.username` and `Digest.response`, given an HTTP request, a username,
@@ -452,14 +740,92 @@ impl Digest {
     /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
     pub fn validate_using_userhash_and_password(&self,
                                                 method: Method,
-                                                entity_body: String,
+                                                entity_body: &[u8],
                                                 username: Username,
                                                 password: String)
-                                                -> bool {
+                                                -> Result<(), ValidationError> {
+        let entity_hash = self.entity_hash(entity_body)?;
+        self.validate_using_userhash_password_and_entity_hash(method,
+                                                              &entity_hash,
+                                                              username,
+                                                              password)
+    }
+
+    /// Like `validate_using_userhash_and_password`, but given the hexadecimal hash of the entity
+    /// body (generated via `EntityBodyHasher`) instead of the entity body itself.
+    pub fn validate_using_userhash_password_and_entity_hash(&self,
+                                                            method: Method,
+                                                            entity_hash: &str,
+                                                            username: Username,
+                                                            password: String)
+                                                            -> Result<(), ValidationError> {
         if self.userhash && !self.validate_userhash(username.clone()) {
-            return false;
+            return Err(ValidationError::UserhashMismatch);
+        }
+        self.validate_using_username_password_and_entity_hash(method,
+                                                              entity_hash,
+                                                              username,
+                                                              password)
+    }
+
+    /// Validates a `Digest.username` and `Digest.response`, given an HTTP request and a store
+    /// to look up the user's credentials in. If `userhash` is `true`, the user is looked up by
+    /// userhash.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn validate_using_credential_store<S>(&self,
+                                              method: Method,
+                                              entity_body: &[u8],
+                                              store: &S)
+                                              -> Result<(), ValidationError>
+        where S: CredentialStore + ?Sized
+    {
+        let entity_hash = self.entity_hash(entity_body)?;
+        self.validate_using_credential_store_and_entity_hash(method, &entity_hash, store)
+    }
+
+    /// Like `validate_using_credential_store`, but given the hexadecimal hash of the entity body
+    /// (generated via `EntityBodyHasher`) instead of the entity body itself.
+    pub fn validate_using_credential_store_and_entity_hash<S>(&self,
+                                                              method: Method,
+                                                              entity_hash: &str,
+                                                              store: &S)
+                                                              -> Result<(), ValidationError>
+        where S: CredentialStore + ?Sized
+    {
+        self.supported_algorithm()?;
+        let username = match self.username {
+            Username::Plain(ref userhash) if self.userhash => {
+                match store.username_for_userhash(userhash, &self.realm, &self.algorithm) {
+                    Some(username) => username,
+                    None => return Err(ValidationError::UnknownUser),
+                }
+            }
+            Username::Plain(ref username) => username.clone(),
+            Username::Encoded(ref encoded) => {
+                match String::from_utf8(encoded.value.clone()) {
+                    Ok(username) => username,
+                    Err(_) => return Err(Error::MalformedParameter("username*").into()),
+                }
+            }
+        };
+        if self.userhash && !self.validate_userhash(Username::Plain(username.clone())) {
+            return Err(ValidationError::UserhashMismatch);
+        }
+        match store.credential(&username, &self.realm, &self.algorithm) {
+            Some(Credential::Password(password)) => {
+                self.validate_using_username_password_and_entity_hash(method,
+                                                                      entity_hash,
+                                                                      Username::Plain(username),
+                                                                      password)
+            }
+            Some(Credential::HashedA1(simple_hashed_a1)) => {
+                let hashed_a1 = self.hashed_a1_from_simple(simple_hashed_a1)?;
+                self.validate_using_hashed_a1_and_entity_hash(method, entity_hash, hashed_a1)
+            }
+            None => Err(ValidationError::UnknownUser),
         }
-        self.validate_using_username_and_password(method, entity_body, username, password)
     }
 
     /// Validates a `Digest.response`, given an HTTP request and a hexadecimal digest of an
@@ -472,13 +838,95 @@ impl Digest {
     /// generation.
     pub fn validate_using_hashed_a1(&self,
                                     method: Method,
-                                    entity_body: String,
+                                    entity_body: &[u8],
                                     a1: String)
-                                    -> bool {
-        if let Ok(hex_digest) = self.using_hashed_a1(method, entity_body, a1) {
-            hex_digest == self.response
+                                    -> Result<(), ValidationError> {
+        let entity_hash = self.entity_hash(entity_body)?;
+        self.validate_using_hashed_a1_and_entity_hash(method, &entity_hash, a1)
+    }
+
+    /// Like `validate_using_hashed_a1`, but given the hexadecimal hash of the entity body
+    /// (generated via `EntityBodyHasher`) instead of the entity body itself.
+    pub fn validate_using_hashed_a1_and_entity_hash(&self,
+                                                    method: Method,
+                                                    entity_hash: &str,
+                                                    a1: String)
+                                                    -> Result<(), ValidationError> {
+        let hex_digest = self.using_hashed_a1_and_entity_hash(method, entity_hash, a1)?;
+        self.validate_response(hex_digest)
+    }
+
+    /// Validates that `Digest.realm` is the realm of the protection space.
+    pub fn validate_realm(&self, realm: &str) -> Result<(), ValidationError> {
+        if self.realm == realm {
+            Ok(())
+        } else {
+            Err(ValidationError::RealmMismatch)
+        }
+    }
+
+    /// Validates that `Digest.request_uri` identifies the same resource as the Request-URI in the
+    /// request line, as required by
+    /// [RFC 7616, section 3.4.6](https://tools.ietf.org/html/rfc7616#section-3.4.6). This
+    /// prevents the credentials from being reused for other resources.
+    ///
+    /// Either URI can be in absolute-form or origin-form. Both URIs are normalized before they
+    /// are compared, including percent-encoding, but the query strings must otherwise be equal.
+    pub fn validate_request_uri(&self, request_uri: &RequestUri) -> Result<(), ValidationError> {
+        match (uri::parse(&self.request_uri), uri::from_request_uri(request_uri)) {
+            (Some(ref expected), Some(ref actual)) if uri::matches(expected, actual) => Ok(()),
+            _ => Err(ValidationError::UriMismatch),
+        }
+    }
+
+    /// Validates that `Digest.nonce` was issued by the server and is still valid.
+    pub fn validate_nonce<M: NonceManager + ?Sized>(&self,
+                                                    manager: &M,
+                                                    context: &NonceContext)
+                                                    -> Result<(), ValidationError> {
+        match manager.validate(&self.nonce, context) {
+            NonceStatus::Valid => Ok(()),
+            NonceStatus::Stale => Err(ValidationError::StaleNonce),
+            NonceStatus::Invalid => Err(ValidationError::UnknownNonce),
+        }
+    }
+
+    /// Validates that the nonce count has not been used with `Digest.nonce` before, and records
+    /// it. Nonce counts are only checked when a quality of protection is specified.
+    ///
+    /// Only call this after the `response` has been validated, or use
+    /// `validate_response_and_nonce_count`: otherwise, a forged request with a high nonce count
+    /// causes every later request from the real client to be rejected as a replay.
+    pub fn validate_nonce_count<G: ReplayGuard + ?Sized>(&self,
+                                                         guard: &G)
+                                                         -> Result<(), ValidationError> {
+        if self.qop.is_none() {
+            return Ok(());
+        }
+        if self.client_nonce.is_none() {
+            return Err(ValidationError::Malformed(Error::MissingParameter("cnonce")));
+        }
+        if self.nonce_count.is_none() {
+            return Err(ValidationError::Malformed(Error::MissingParameter("nc")));
+        }
+        if guard.accept_digest(self) {
+            Ok(())
         } else {
-            false
+            Err(ValidationError::ReplayedNonceCount)
         }
     }
+
+    /// Validates the `response` via `validate_response` (for example, a closure that calls
+    /// `validate_using_password`), and only if it is valid, validates and records the nonce count
+    /// via `validate_nonce_count`.
+    pub fn validate_response_and_nonce_count<G, F>(&self,
+                                                   guard: &G,
+                                                   validate_response: F)
+                                                   -> Result<(), ValidationError>
+        where G: ReplayGuard + ?Sized,
+              F: FnOnce(&Digest) -> Result<(), ValidationError>
+    {
+        validate_response(self)?;
+        self.validate_nonce_count(guard)
+    }
 }
@@ -19,11 +19,22 @@
 // THE SOFTWARE.
 
 #![cfg(test)]
+use authentication_info::AuthenticationInfo;
+use error::{Error, ValidationError};
 use hyper::header::{Authorization, Header, Scheme};
 use hyper::header::parsing::parse_extended_value;
 use hyper::method::Method;
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+use nonce::{HmacNonceManager, NonceContext, NonceManager, NonceStatus};
+use precis::Profile;
+use replay::InMemoryReplayGuard;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
 use super::{Digest, Username};
-use super::super::types::{HashAlgorithm, Qop};
+use super::super::types::{EntityBodyHasher, HashAlgorithm, NonceCount, Qop};
 use super::test_helper::{assert_header_parsing_error, assert_parsed_header_equal,
                          assert_serialized_header_equal, parse_digest_header,
                          rfc2069_a1_digest_header, rfc2069_a2_digest_header, rfc2069_username,
@@ -51,6 +62,58 @@ fn test_display_sha512_256session_for_hashalgorithm() {
                format!("{}", HashAlgorithm::SHA512256Session))
 }
 
+#[test]
+fn test_entity_body_hasher() {
+    let mut hasher = EntityBodyHasher::new(&HashAlgorithm::SHA256)
+        .expect("Could not create hasher");
+    hasher.update(b"foo=");
+    hasher.update(b"");
+    hasher.update(b"bar");
+    assert_eq!(HashAlgorithm::SHA256.hex_digest(b"foo=bar".to_vec()).expect("Could not hash"),
+               hasher.hex_digest())
+}
+
+#[test]
+fn test_entity_body_hasher_with_sha512_256() {
+    let mut hasher = EntityBodyHasher::new(&HashAlgorithm::SHA512256)
+        .expect("Could not create hasher");
+    io::copy(&mut &b"foo=bar"[..], &mut hasher).expect("Could not hash entity body");
+    let hex_digest = hasher.hex_digest();
+    assert_eq!(64, hex_digest.len());
+    assert_eq!(HashAlgorithm::SHA512256.hex_digest(b"foo=bar".to_vec()).expect("Could not hash"),
+               hex_digest)
+}
+
+#[test]
+fn test_hex_digest_with_sha512_256() {
+    let expected = "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23";
+    assert_eq!(expected,
+               HashAlgorithm::SHA512256.hex_digest(b"abc".to_vec()).expect("Could not hash"));
+    assert_eq!(expected,
+               HashAlgorithm::SHA512256Session.hex_digest(b"abc".to_vec())
+                   .expect("Could not hash"))
+}
+
+#[test]
+fn test_hex_digest_with_sha256_session() {
+    assert_eq!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+               HashAlgorithm::SHA256Session.hex_digest(b"abc".to_vec())
+                   .expect("Could not hash"))
+}
+
+#[test]
+fn test_hex_digest_with_unknown_algorithm() {
+    let algorithm = HashAlgorithm::Unknown("SHA3-256".to_owned());
+    assert_eq!(Err(Error::UnsupportedAlgorithm("SHA3-256".to_owned())),
+               algorithm.hex_digest(b"abc".to_vec()))
+}
+
+#[test]
+fn test_entity_body_hasher_with_unknown_algorithm() {
+    let algorithm = HashAlgorithm::Unknown("SHA3-256".to_owned());
+    assert!(EntityBodyHasher::new(&algorithm).is_err())
+}
+
 #[test]
 fn test_scheme() {
     assert_eq!(Digest::scheme(), Some("Digest"))
@@ -150,6 +213,40 @@ fn test_parse_header_with_invalid_charset() {
                                  opaque=\"5ccc069c403ebaf9f0171e9517f40e41\", charset=invalid")
 }
 
+#[test]
+fn test_parse_errors() {
+    let parameters = "realm=\"testrealm@host.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                      uri=\"/dir/index.html\", response=\"6629fae49393a05397450978507c4ef1\"";
+    assert_eq!(Err(Error::MissingParameter("username")), Digest::from_str(parameters));
+    assert_eq!(Err(Error::MissingParameter("realm")),
+               Digest::from_str("username=\"Mufasa\", nonce=\"abc\", uri=\"/\", \
+                                 response=\"def\""));
+    assert_eq!(Err(Error::ConflictingParameters("username", "username*")),
+               Digest::from_str(&format!("username=\"a\", username*=UTF-8''a, {}", parameters)));
+    assert_eq!(Err(Error::MalformedParameter("algorithm")),
+               Digest::from_str(&format!("username=\"a\", algorithm=\"\", {}", parameters)));
+    assert_eq!(Err(Error::UnsupportedCharset("latin1".to_owned())),
+               Digest::from_str(&format!("username=\"a\", charset=latin1, {}", parameters)));
+    assert_eq!(Err(Error::UnsupportedCharset("ISO-8859-1".to_owned())),
+               Digest::from_str(&format!("username*=ISO-8859-1''a, {}", parameters)));
+    assert_eq!(Err(Error::MalformedParameter("nc")),
+               Digest::from_str(&format!("username=\"a\", nc=01, {}", parameters)));
+    assert_eq!(Err(Error::MalformedParameter("qop")),
+               Digest::from_str(&format!("username=\"a\", qop=\" auth\", {}", parameters)))
+}
+
+#[test]
+fn test_parse_header_with_quoted_tokens() {
+    // Some clients, such as Python's requests, quote the algorithm and qop.
+    assert_parsed_header_equal(Authorization(rfc2617_digest_header(HashAlgorithm::MD5)),
+                               "Digest username=\"Mufasa\", realm=\"testrealm@host.com\", \
+                                nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                                uri=\"/dir/index.html\", algorithm=\"MD5\", qop=\"auth\", \
+                                nc=00000001, cnonce=\"0a4f113b\", \
+                                response=\"6629fae49393a05397450978507c4ef1\", \
+                                opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"")
+}
+
 #[test]
 fn test_parse_header_with_md5_algorithm() {
     let expected = Authorization(rfc2617_digest_header(HashAlgorithm::MD5));
@@ -187,13 +284,15 @@ fn test_parse_header_with_md5_sess_algorithm() {
 }
 
 #[test]
-fn test_parse_header_with_invalid_algorithm() {
-    assert_header_parsing_error("Digest username=\"Mufasa\", realm=\"testrealm@host.com\", \
-                                 nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
-                                 uri=\"/dir/index.html\", algorithm=invalid, qop=auth, \
-                                 nc=00000001, cnonce=\"0a4f113b\", \
-                                 response=\"6629fae49393a05397450978507c4ef1\", \
-                                 opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"")
+fn test_parse_header_with_unknown_algorithm() {
+    let digest = rfc2617_digest_header(HashAlgorithm::Unknown("invalid".to_owned()));
+    assert_parsed_header_equal(Authorization(digest),
+                               "Digest username=\"Mufasa\", realm=\"testrealm@host.com\", \
+                                nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                                uri=\"/dir/index.html\", algorithm=invalid, qop=auth, \
+                                nc=00000001, cnonce=\"0a4f113b\", \
+                                response=\"6629fae49393a05397450978507c4ef1\", \
+                                opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"")
 }
 
 #[test]
@@ -219,6 +318,16 @@ fn test_parse_header_with_bad_qop() {
                                  opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"")
 }
 
+#[test]
+fn test_parse_header_with_multiple_qops() {
+    assert_header_parsing_error("Digest username=\"Mufasa\", realm=\"testrealm@host.com\", \
+                                 nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                                 uri=\"/dir/index.html\", qop=\"auth,auth-int\", nc=00000001, \
+                                 cnonce=\"0a4f113b\", \
+                                 response=\"6629fae49393a05397450978507c4ef1\", \
+                                 opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"")
+}
+
 #[test]
 fn test_parse_header_with_bad_nonce_count() {
     assert_header_parsing_error("Digest username=\"Mufasa\", realm=\"testrealm@host.com\", \
@@ -275,12 +384,12 @@ fn test_fmt_scheme_for_md5_sess_algorithm() {
 
 #[test]
 fn test_fmt_scheme_with_userhash() {
-    let userhash = "488869477bf257147b804c45308cd62ac4e25eb717b12b298c79e62dcea254ec";
+    let userhash = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b";
     let digest = rfc7616_sha512_256_header(userhash.to_owned(), true);
     let expected = format!("Authorization: Digest username=\"{}\", realm=\"api@example.org\", \
                             nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", nc=00000001, \
-                            response=\"ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f60\
-                            7a79dd\", uri=\"/doe.json\", algorithm=SHA-512-256, qop=auth, \
+                            response=\"3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1c\
+                            ec68a5\", uri=\"/doe.json\", algorithm=SHA-512-256, qop=auth, \
                             cnonce=\"NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v\", \
                             opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
                             charset=UTF-8, userhash=true",
@@ -295,29 +404,118 @@ fn test_fmt_scheme_with_extended_username() {
     let expected = "Authorization: Digest username*=UTF-8''J%C3%A4s%C3%B8n%20Doe, \
                     realm=\"api@example.org\", \
                     nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", nc=00000001, \
-                    response=\"ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f607a79dd\", \
+                    response=\"3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5\", \
                     uri=\"/doe.json\", algorithm=SHA-512-256, qop=auth, \
                     cnonce=\"NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v\", \
                     opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", charset=UTF-8";
     assert_serialized_header_equal(digest, expected)
 }
 
+#[test]
+fn test_round_trip_with_percent_encoded_values() {
+    let data = "Digest username=\"Mufasa%20\", realm=\"testrealm@host.com\", \
+                nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093%2F\", nc=00000001, \
+                response=\"6629fae49393a05397450978507c4ef1\", \
+                uri=\"/dir/index%20page.html?q=%25\", algorithm=MD5, qop=auth, \
+                cnonce=\"0a4f113b%00\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41%\"";
+    let header = parse_digest_header(data);
+    assert_eq!(Username::Plain("Mufasa%20".to_owned()), header.0.username);
+    assert_eq!("dcd98b7102dd2f0e8b11d0f600bfb0c093%2F", header.0.nonce);
+    assert_eq!("/dir/index%20page.html?q=%25", header.0.request_uri);
+    assert_eq!(Some("0a4f113b%00".to_owned()), header.0.client_nonce);
+    assert_eq!(Some("5ccc069c403ebaf9f0171e9517f40e41%".to_owned()), header.0.opaque);
+    assert_serialized_header_equal(header.0, &format!("Authorization: {}", data)[..])
+}
+
+#[test]
+fn test_round_trip_with_extended_username() {
+    let data = "Digest username*=UTF-8''J%C3%A4s%C3%B8n%20Doe, realm=\"api@example.org\", \
+                nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+                response=\"3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5\", \
+                uri=\"/doe.json\", algorithm=SHA-512-256";
+    let header = parse_digest_header(data);
+    assert_eq!(rfc7616_username(), header.0.username);
+    assert_serialized_header_equal(header.0, &format!("Authorization: {}", data)[..])
+}
+
+#[test]
+fn test_round_trip_with_extensions() {
+    let data = "Digest username=\"Mufasa\", realm=\"testrealm@host.com\", \
+                nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                response=\"1949323746fe6a43ef61f9606e7febea\", uri=\"/dir/index.html\", \
+                algorithm=MD5, x-device=\"kitchen tablet\", x-session=9";
+    let header = parse_digest_header(data);
+    assert_eq!(2, header.0.extensions.len());
+    assert_eq!(Some("kitchen tablet"), header.0.extensions.get("x-device"));
+    assert_eq!(Ok(()),
+               header.0.validate_using_password(Method::Get, b"", "CircleOfLife".to_owned()));
+    assert_serialized_header_equal(header.0, &format!("Authorization: {}", data)[..])
+}
+
+#[test]
+fn test_extensions_cannot_shadow_parameters() {
+    let mut digest = rfc2069_a1_digest_header();
+    for name in &["realm", "Username", "username*", "NC", "userhash"] {
+        assert_eq!(Err(Error::ReservedParameter(name.to_string())),
+                   digest.extensions.insert(name, "value"));
+    }
+    assert_eq!(Err(Error::MalformedParameters),
+               digest.extensions.insert("x vendor", "value"));
+    assert!(digest.extensions.is_empty());
+    assert!(digest.extensions.is_reserved("Response"));
+}
+
+#[test]
+fn test_insert_extensions() {
+    let mut digest = rfc2069_a1_digest_header();
+    assert_eq!(Ok(None), digest.extensions.insert("x-a", "1"));
+    assert_eq!(Ok(None), digest.extensions.insert("x-b", "two words"));
+    assert_eq!(Ok(Some("1".to_owned())), digest.extensions.insert("X-A", "3"));
+    let names: Vec<&str> = digest.extensions.iter().map(|p| &p.name[..]).collect();
+    assert_eq!(vec!["X-A", "x-b"], names);
+    assert_serialized_header_equal(digest.clone(),
+                                   "Authorization: Digest username=\"Mufasa\", \
+                                    realm=\"testrealm@host.com\", \
+                                    nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                                    response=\"1949323746fe6a43ef61f9606e7febea\", \
+                                    uri=\"/dir/index.html\", algorithm=MD5, X-A=3, \
+                                    x-b=\"two words\"");
+    assert_eq!(Some("3".to_owned()), digest.extensions.remove("x-a"));
+    assert_eq!(None, digest.extensions.get("x-a"));
+    assert_eq!(1, digest.extensions.len())
+}
+
+#[test]
+fn test_parse_header_with_quoted_extended_username() {
+    assert_header_parsing_error("Digest username*=\"UTF-8''J%C3%A4s%C3%B8n%20Doe\", \
+                                 realm=\"api@example.org\", nonce=\"abc\", uri=\"/doe.json\", \
+                                 response=\"def\"")
+}
+
 #[test]
 fn test_userhash() {
-    let expected = "488869477bf257147b804c45308cd62ac4e25eb717b12b298c79e62dcea254ec".to_owned();
+    let expected = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b".to_owned();
     if let Username::Encoded(username) = rfc7616_username() {
         let actual = Digest::userhash(&HashAlgorithm::SHA512256,
                                       username.value,
-                                      "api@example.org".to_owned());
+                                      "api@example.org".to_owned())
+            .expect("Could not generate userhash");
         assert_eq!(expected, actual);
     } else {
         panic!("Bad username");
     }
 }
 
+#[test]
+fn test_userhash_with_unknown_algorithm() {
+    let algorithm = HashAlgorithm::Unknown("SHA3-256".to_owned());
+    assert_eq!(Err(Error::UnsupportedAlgorithm("SHA3-256".to_owned())),
+               Digest::userhash(&algorithm, b"Mufasa".to_vec(), "testrealm@host.com".to_owned()))
+}
+
 #[test]
 fn test_validate_userhash() {
-    let userhash = "488869477bf257147b804c45308cd62ac4e25eb717b12b298c79e62dcea254ec".to_owned();
+    let userhash = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b".to_owned();
     let digest = rfc7616_sha512_256_header(userhash, true);
 
     assert!(digest.validate_userhash(rfc7616_username()));
@@ -332,6 +530,14 @@ fn test_validate_userhash_with_plain_username() {
     assert!(digest.validate_userhash(rfc2069_username()));
 }
 
+#[test]
+fn test_validate_uppercase_userhash() {
+    let userhash = "793263CAABB707A56211940D90411EA4A575ADECCB7E360AEB624ED06ECE9B0B".to_owned();
+    let digest = rfc7616_sha512_256_header(userhash, true);
+
+    assert!(digest.validate_userhash(rfc7616_username()));
+}
+
 #[test]
 fn test_validate_userhash_with_invalid_encoded_username() {
     let mut digest = rfc7616_digest_header(HashAlgorithm::SHA256, "");
@@ -341,6 +547,15 @@ fn test_validate_userhash_with_invalid_encoded_username() {
     assert!(!digest.validate_userhash(rfc7616_username()));
 }
 
+#[test]
+fn test_validate_userhash_with_decomposed_username() {
+    let userhash = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b".to_owned();
+    let digest = rfc7616_sha512_256_header(userhash, true);
+    let decomposed = Username::Plain("Ja\u{308}s\u{f8}n Doe".to_owned());
+
+    assert!(digest.validate_userhash(decomposed));
+}
+
 #[test]
 fn test_simple_hashed_a1() {
     let digest = rfc2069_a1_digest_header();
@@ -348,7 +563,8 @@ fn test_simple_hashed_a1() {
     let actual = Digest::simple_hashed_a1(&digest.algorithm,
                                           digest.username,
                                           digest.realm,
-                                          "Circle Of Life".to_owned());
+                                          "Circle Of Life".to_owned())
+        .expect("Could not hash");
     assert_eq!(expected, actual)
 }
 
@@ -362,6 +578,53 @@ fn test_a1() {
     assert_eq!(expected, a1.unwrap())
 }
 
+#[test]
+fn test_a1_normalizes_utf8_credentials() {
+    let digest = rfc7616_sha512_256_header("Ja\u{308}s\u{f8}n Doe".to_owned(), false);
+    let a1 = digest.a1(digest.username.clone(), "Sa\u{301}o".to_owned());
+    let expected = "J\u{e4}s\u{f8}n Doe:api@example.org:S\u{e1}o".to_owned().into_bytes();
+    assert_eq!(Ok(expected), a1)
+}
+
+#[test]
+fn test_a1_without_charset_is_not_normalized() {
+    let mut digest = rfc7616_sha512_256_header("Ja\u{308}s\u{f8}n Doe".to_owned(), false);
+    digest.charset = None;
+    let a1 = digest.a1(digest.username.clone(), "Sa\u{301}o".to_owned());
+    let expected = "Ja\u{308}s\u{f8}n Doe:api@example.org:Sa\u{301}o".to_owned().into_bytes();
+    assert_eq!(Ok(expected), a1)
+}
+
+#[test]
+fn test_prepare_credentials() {
+    let digest = rfc7616_sha512_256_header("Ja\u{308}s\u{f8}n Doe".to_owned(), false);
+    let prepared = digest.prepare_credentials(Profile::UsernameCaseMapped,
+                                              digest.username.clone(),
+                                              "Secret,\u{a0}or not?".to_owned());
+    let expected = (Username::Plain("j\u{e4}s\u{f8}n doe".to_owned()),
+                    "Secret, or not?".to_owned());
+    assert_eq!(Ok(expected), prepared)
+}
+
+#[test]
+fn test_prepare_credentials_without_charset() {
+    let mut digest = rfc7616_sha512_256_header("Ja\u{308}s\u{f8}n Doe".to_owned(), false);
+    digest.charset = None;
+    let prepared = digest.prepare_credentials(Profile::UsernameCaseMapped,
+                                              digest.username.clone(),
+                                              "Secret,\u{a0}or not?".to_owned());
+    assert_eq!(Ok((digest.username.clone(), "Secret,\u{a0}or not?".to_owned())), prepared)
+}
+
+#[test]
+fn test_prepare_credentials_with_disallowed_username() {
+    let digest = rfc7616_sha512_256_header("foo\u{2665}".to_owned(), false);
+    let prepared = digest.prepare_credentials(Profile::UsernameCasePreserved,
+                                              digest.username.clone(),
+                                              "Secret, or not?".to_owned());
+    assert_eq!(Err(Error::DisallowedCharacter('\u{2665}')), prepared)
+}
+
 #[test]
 fn test_a1_for_md5_sess() {
     let digest = rfc2617_digest_header(HashAlgorithm::MD5Session);
@@ -375,13 +638,24 @@ fn test_a1_for_md5_sess() {
     assert_eq!(expected, a1.unwrap())
 }
 
+#[test]
+fn test_hashed_a1_from_simple() {
+    let simple_hashed_a1 = "939e7578ed9e3c518a452acee763bce9".to_owned();
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Ok(simple_hashed_a1.clone()),
+               digest.hashed_a1_from_simple(simple_hashed_a1.clone()));
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5Session);
+    let expected = digest.hashed_a1(digest.username.clone(), "Circle Of Life".to_owned());
+    assert_eq!(expected, digest.hashed_a1_from_simple(simple_hashed_a1))
+}
+
 #[test]
 fn test_a1_for_md5_sess_without_client_nonce() {
     let mut digest = rfc2617_digest_header(HashAlgorithm::MD5Session);
     digest.client_nonce = None;
     let password = "Circle Of Life".to_owned();
     let a1 = digest.a1(digest.username.clone(), password);
-    assert!(a1.is_err())
+    assert_eq!(Err(Error::MissingClientNonce), a1)
 }
 
 #[test]
@@ -406,7 +680,7 @@ fn test_hashed_a1_for_md5_sess_without_client_nonce() {
 fn test_a2() {
     let digest = rfc2069_a2_digest_header();
     let expected = "GET:/dir/index.html";
-    let actual = digest.a2(Method::Get, "".to_owned());
+    let actual = digest.a2("GET", "");
     assert_eq!(expected, actual)
 }
 
@@ -414,7 +688,7 @@ fn test_a2() {
 fn test_hashed_a2() {
     let digest = rfc2069_a2_digest_header();
     let expected = "39aff3a2bab6126f332b942af96d3366";
-    let actual = digest.hashed_a2(Method::Get, "".to_owned());
+    let actual = digest.hashed_a2(Method::Get, "").expect("Could not hash");
     assert_eq!(expected, actual)
 }
 
@@ -432,7 +706,7 @@ fn test_from_header() {
                                    .to_vec()][..])
             .unwrap();
 
-    let hex_digest = header.0.using_password(Method::Get, "".to_owned(), password);
+    let hex_digest = header.0.using_password(Method::Get, b"", password);
     assert!(hex_digest.is_ok());
     assert_eq!(header.0.response, hex_digest.unwrap())
 }
@@ -444,7 +718,7 @@ fn test_from_passport_http_header() {
                                       nonce=\"NOIEDJ3hJtqSKaty8KF8xlkaYbItAkiS\", uri=\"/\", \
                                       response=\"22e3e0a9bbefeb9d229905230cb9ddc8\"");
 
-    let hex_digest = header.0.using_password(Method::Head, "".to_owned(), password);
+    let hex_digest = header.0.using_password(Method::Head, b"", password);
     assert!(hex_digest.is_ok());
     assert_eq!(header.0.response, hex_digest.unwrap())
 }
@@ -454,7 +728,7 @@ fn test_using_password_and_md5_session_sans_client_nonce() {
     let password = "Circle Of Life".to_owned();
     let mut digest = rfc2617_digest_header(HashAlgorithm::MD5Session);
     digest.client_nonce = None;
-    let hex_digest = digest.using_password(Method::Get, "".to_owned(), password);
+    let hex_digest = digest.using_password(Method::Get, b"", password);
     assert!(hex_digest.is_err())
 }
 
@@ -464,7 +738,7 @@ fn test_using_password_and_sha256() {
     let digest = rfc7616_digest_header(HashAlgorithm::SHA256,
                                        "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db58\
                                         56cb6c1");
-    let hex_digest = digest.using_password(Method::Get, "".to_owned(), password);
+    let hex_digest = digest.using_password(Method::Get, b"", password);
     assert!(hex_digest.is_ok());
     assert_eq!(digest.response, hex_digest.unwrap())
 }
@@ -473,7 +747,7 @@ fn test_using_password_and_sha256() {
 fn test_using_hashed_a1() {
     let hashed_a1 = "939e7578ed9e3c518a452acee763bce9".to_owned();
     let digest = rfc2617_digest_header(HashAlgorithm::MD5);
-    let hex_digest = digest.using_hashed_a1(Method::Get, "".to_owned(), hashed_a1);
+    let hex_digest = digest.using_hashed_a1(Method::Get, b"", hashed_a1);
     assert!(hex_digest.is_ok());
     assert_eq!(digest.response, hex_digest.unwrap())
 }
@@ -484,7 +758,49 @@ fn test_using_hashed_a1_with_auth_int_qop() {
     let expected = "7b9be1c2def9d4ad657b26ac8bc651a0".to_owned();
     let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
     digest.qop = Some(Qop::AuthInt);
-    let hex_digest = digest.using_hashed_a1(Method::Get, "foo=bar".to_owned(), hashed_a1);
+    let hex_digest = digest.using_hashed_a1(Method::Get, b"foo=bar", hashed_a1);
+    assert!(hex_digest.is_ok());
+    assert_eq!(expected, hex_digest.unwrap())
+}
+
+#[test]
+fn test_using_hashed_a1_and_entity_hash() {
+    let hashed_a1 = "939e7578ed9e3c518a452acee763bce9".to_owned();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.qop = Some(Qop::AuthInt);
+    let mut hasher = EntityBodyHasher::new(&digest.algorithm).expect("Could not create hasher");
+    hasher.update(b"foo=");
+    hasher.update(b"bar");
+    let entity_hash = hasher.hex_digest();
+    let hex_digest = digest.using_hashed_a1_and_entity_hash(Method::Get, &entity_hash, hashed_a1);
+    assert_eq!(Ok("7b9be1c2def9d4ad657b26ac8bc651a0".to_owned()), hex_digest)
+}
+
+#[test]
+fn test_validate_using_password_and_entity_hash() {
+    let password = "Circle Of Life".to_owned();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.qop = Some(Qop::AuthInt);
+    digest.response = "7b9be1c2def9d4ad657b26ac8bc651a0".to_owned();
+    let mut hasher = EntityBodyHasher::new(&digest.algorithm).expect("Could not create hasher");
+    hasher.update(b"foo=bar");
+    let entity_hash = hasher.hex_digest();
+    assert!(digest.validate_using_password_and_entity_hash(Method::Get,
+                                                           &entity_hash,
+                                                           password.clone())
+        .is_ok());
+    assert_eq!(Err(ValidationError::WrongCredentials),
+               digest.validate_using_password_and_entity_hash(Method::Get, "", password))
+}
+
+#[test]
+fn test_using_hashed_a1_with_auth_int_qop_and_binary_entity_body() {
+    let hashed_a1 = "939e7578ed9e3c518a452acee763bce9".to_owned();
+    let expected = "0bfa18f49bdb3a7ffffa39c28bf13e93".to_owned();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.qop = Some(Qop::AuthInt);
+    let entity_body = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', 0x00, 0xff];
+    let hex_digest = digest.using_hashed_a1(Method::Get, &entity_body, hashed_a1);
     assert!(hex_digest.is_ok());
     assert_eq!(expected, hex_digest.unwrap())
 }
@@ -495,7 +811,7 @@ fn test_using_hashed_a1_with_auth_int_qop_sans_nonce_count() {
     let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
     digest.qop = Some(Qop::AuthInt);
     digest.nonce_count = None;
-    let hex_digest = digest.using_hashed_a1(Method::Get, "foo=bar".to_owned(), hashed_a1);
+    let hex_digest = digest.using_hashed_a1(Method::Get, b"foo=bar", hashed_a1);
     assert!(hex_digest.is_err())
 }
 
@@ -505,7 +821,7 @@ fn test_using_hashed_a1_with_auth_int_qop_sans_client_nonce() {
     let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
     digest.qop = Some(Qop::AuthInt);
     digest.client_nonce = None;
-    let hex_digest = digest.using_hashed_a1(Method::Get, "foo=bar".to_owned(), hashed_a1);
+    let hex_digest = digest.using_hashed_a1(Method::Get, b"foo=bar", hashed_a1);
     assert!(hex_digest.is_err())
 }
 
@@ -515,11 +831,118 @@ fn test_using_hashed_a1_sans_qop() {
     let expected = "670fd8c2df070c60b045671b8b24ff02".to_owned();
     let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
     digest.qop = None;
-    let hex_digest = digest.using_hashed_a1(Method::Get, "".to_owned(), hashed_a1);
+    let hex_digest = digest.using_hashed_a1(Method::Get, b"", hashed_a1);
     assert!(hex_digest.is_ok());
     assert_eq!(expected, hex_digest.unwrap())
 }
 
+#[test]
+fn test_authentication_info_using_password() {
+    let password = "Circle Of Life".to_owned();
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let next_nonce = Some("fedcba".to_owned());
+    let info = digest.authentication_info_using_password(b"", password, next_nonce)
+        .expect("Could not generate Authentication-Info");
+    assert_eq!(Some("376602cfd2f4e8e5e78b948a85263e85".to_owned()), info.digest);
+    assert_eq!(Some("fedcba".to_owned()), info.next_nonce);
+    assert_eq!(digest.qop, info.qop);
+    assert_eq!(digest.client_nonce, info.client_nonce);
+    assert_eq!(digest.nonce_count, info.nonce_count)
+}
+
+#[test]
+fn test_authentication_info_using_password_and_sha256() {
+    let password = "Circle of Life".to_owned();
+    let digest = rfc7616_digest_header(HashAlgorithm::SHA256,
+                                       "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db58\
+                                        56cb6c1");
+    let info = digest.authentication_info_using_password(b"", password, None)
+        .expect("Could not generate Authentication-Info");
+    assert_eq!(Some("86d3b25618d41854ca5039a5d7e53ff6355d5134a9b1fb088a78ac3c462195a0".to_owned()),
+               info.digest);
+    assert_eq!(None, info.next_nonce)
+}
+
+#[test]
+fn test_authentication_info_with_userhash() {
+    let password = "Secret, or not?".to_owned();
+    let userhash = "793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b".to_owned();
+    let digest = rfc7616_sha512_256_header(userhash, true);
+    assert_eq!(Err(Error::MissingUsername),
+               digest.authentication_info_using_password(b"", password.clone(), None));
+
+    let info = digest.authentication_info_using_username_and_password(b"",
+                                                                      rfc7616_username(),
+                                                                      password.clone(),
+                                                                      None)
+        .expect("Could not generate Authentication-Info");
+    let mut plain = digest.clone();
+    plain.username = rfc7616_username();
+    plain.userhash = false;
+    let expected = plain.authentication_info_using_password(b"", password.clone(), None)
+        .expect("Could not generate Authentication-Info");
+    assert_eq!(expected.digest, info.digest);
+    assert!(digest.validate_authentication_info(&info, b"", rfc7616_username(), password))
+}
+
+#[test]
+fn test_authentication_info_using_hashed_a1_with_auth_int_qop() {
+    let hashed_a1 = "939e7578ed9e3c518a452acee763bce9".to_owned();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.qop = Some(Qop::AuthInt);
+    let info = digest.authentication_info_using_hashed_a1(b"Hello", hashed_a1, None)
+        .expect("Could not generate Authentication-Info");
+    assert_eq!(Some("c3d9111a6aa7d2b9977fe89f0ffd9340".to_owned()), info.digest);
+    assert_eq!(Some(Qop::AuthInt), info.qop)
+}
+
+#[test]
+fn test_authentication_info_using_hashed_a1_sans_client_nonce() {
+    let hashed_a1 = "939e7578ed9e3c518a452acee763bce9".to_owned();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.client_nonce = None;
+    let info = digest.authentication_info_using_hashed_a1(b"", hashed_a1, None);
+    assert!(info.is_err())
+}
+
+#[test]
+fn test_validate_authentication_info() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let info = AuthenticationInfo {
+        digest: Some("376602cfd2f4e8e5e78b948a85263e85".to_owned()),
+        next_nonce: None,
+        qop: Some(Qop::Auth),
+        client_nonce: Some("0a4f113b".to_owned()),
+        nonce_count: Some(NonceCount(1)),
+        extensions: AuthenticationInfo::new_extensions(),
+    };
+    assert!(digest.validate_authentication_info(&info,
+                                                b"",
+                                                rfc2069_username(),
+                                                "Circle Of Life".to_owned()));
+    assert!(!digest.validate_authentication_info(&info,
+                                                 b"",
+                                                 rfc2069_username(),
+                                                 "CircleOfLife".to_owned()))
+}
+
+#[test]
+fn test_validate_authentication_info_with_mismatched_qop() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let info = AuthenticationInfo {
+        digest: Some("376602cfd2f4e8e5e78b948a85263e85".to_owned()),
+        next_nonce: None,
+        qop: Some(Qop::AuthInt),
+        client_nonce: Some("0a4f113b".to_owned()),
+        nonce_count: Some(NonceCount(1)),
+        extensions: AuthenticationInfo::new_extensions(),
+    };
+    assert!(!digest.validate_authentication_info(&info,
+                                                 b"",
+                                                 rfc2069_username(),
+                                                 "Circle Of Life".to_owned()))
+}
+
 #[test]
 fn test_validate_using_password() {
     let password = "Circle of Life".to_owned();
@@ -531,10 +954,11 @@ fn test_validate_using_password() {
                                       response=\"65e4930cfb0b33cb53405ecea0705cec\", \
                                       opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
                                       qop=auth, nc=00000001, cnonce=\"b24ce2519b8cdb10\"");
-    assert!(header.0.validate_using_password(Method::Get, "".to_owned(), password.clone()));
+    assert!(header.0.validate_using_password(Method::Get, b"", password.clone()).is_ok());
     let mut digest = header.0.clone();
     digest.client_nonce = Some("somethingelse".to_owned());
-    assert!(!digest.validate_using_password(Method::Get, "".to_owned(), password));
+    assert_eq!(Err(ValidationError::WrongCredentials),
+               digest.validate_using_password(Method::Get, b"", password));
 }
 
 #[test]
@@ -548,54 +972,186 @@ fn test_validate_using_encoded_username_and_password() {
                                       nc=00000001, \
                                       cnonce=\"NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v\", \
                                       qop=auth, \
-                                      response=\"ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f607a79dd\", \
+                                      response=\"3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5\", \
                                       opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
                                       userhash=false");
-    assert!(header.0.validate_using_password(Method::Get, "".to_owned(), password.clone()));
+    assert!(header.0.validate_using_password(Method::Get, b"", password.clone()).is_ok());
 }
 
 #[test]
 fn test_validate_using_userhash_and_password() {
     // From RFC 7616
     let password = "Secret, or not?".to_owned();
-    let header = parse_digest_header("Digest username=\"488869477bf257147b804c45308cd62ac4e25eb717b12b298c79e62dcea254ec\", \
+    let header = parse_digest_header("Digest username=\"793263caabb707a56211940d90411ea4a575adeccb7e360aeb624ed06ece9b0b\", \
                                       realm=\"api@example.org\", uri=\"/doe.json\", \
                                       algorithm=SHA-512-256, \
                                       nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
                                       nc=00000001, \
                                       cnonce=\"NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v\", \
                                       qop=auth, \
-                                      response=\"ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f607a79dd\", \
+                                      response=\"3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5\", \
                                       opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
                                       charset=UTF-8, userhash=true");
     assert!(header.0.validate_using_userhash_and_password(Method::Get,
-                                                          "".to_owned(),
+                                                          b"",
                                                           rfc7616_username(),
-                                                          password.clone()));
+                                                          password.clone()).is_ok());
 
     let mut digest = header.0.clone();
     digest.userhash = false;
     digest.username = rfc7616_username();
     assert!(digest.validate_using_userhash_and_password(Method::Get,
-                                                        "".to_owned(),
+                                                        b"",
                                                         rfc7616_username(),
-                                                        password.clone()));
+                                                        password.clone()).is_ok());
 
     digest.userhash = true;
     digest.username = Username::Plain("invalid".to_owned());
 
-    assert!(!digest.validate_using_userhash_and_password(Method::Get,
-                                                         "".to_owned(),
-                                                         rfc7616_username(),
-                                                         password.clone()));
+    assert_eq!(Err(ValidationError::UserhashMismatch),
+               digest.validate_using_userhash_and_password(Method::Get,
+                                                           b"",
+                                                           rfc7616_username(),
+                                                           password.clone()));
 }
 
 #[test]
 fn test_validate_using_hashed_a1() {
     let hashed_a1 = "3d78807defe7de2157e2b0b6573a855f".to_owned();
     let mut digest = rfc7616_digest_header(HashAlgorithm::MD5, "8ca523f5e9506fed4657c9700eebdbec");
-    assert!(digest.validate_using_hashed_a1(Method::Get, "".to_owned(), hashed_a1.clone()));
+    assert!(digest.validate_using_hashed_a1(Method::Get, b"", hashed_a1.clone()).is_ok());
 
     digest.client_nonce = Some("different".to_owned());
-    assert!(!digest.validate_using_hashed_a1(Method::Get, "".to_owned(), hashed_a1));
+    assert_eq!(Err(ValidationError::WrongCredentials),
+               digest.validate_using_hashed_a1(Method::Get, b"", hashed_a1));
+}
+
+#[test]
+fn test_validate_using_hashed_a1_sans_client_nonce() {
+    let hashed_a1 = "939e7578ed9e3c518a452acee763bce9".to_owned();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.client_nonce = None;
+    let result = digest.validate_using_hashed_a1(Method::Get, b"", hashed_a1);
+    assert_eq!(Err(ValidationError::Malformed(Error::MissingParameter("cnonce"))), result);
+    assert_eq!(StatusCode::BadRequest, result.unwrap_err().status_code())
+}
+
+#[test]
+fn test_validate_realm() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert!(digest.validate_realm("testrealm@host.com").is_ok());
+    assert_eq!(Err(ValidationError::RealmMismatch),
+               digest.validate_realm("myhost@testrealm.com"))
+}
+
+#[test]
+fn test_validate_request_uri() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let origin_form = RequestUri::AbsolutePath("/dir/index.html".to_owned());
+    assert!(digest.validate_request_uri(&origin_form).is_ok());
+    let url = Url::parse("http://host.com/dir/%69ndex.html").expect("Could not parse URL");
+    assert!(digest.validate_request_uri(&RequestUri::AbsoluteUri(url)).is_ok());
+
+    let other = RequestUri::AbsolutePath("/dir/other.html".to_owned());
+    let result = digest.validate_request_uri(&other);
+    assert_eq!(Err(ValidationError::UriMismatch), result);
+    assert_eq!(StatusCode::BadRequest, result.unwrap_err().status_code())
+}
+
+#[test]
+fn test_validate_request_uri_with_query() {
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.request_uri = "/dir/index.html?page=1".to_owned();
+    let same = RequestUri::AbsolutePath("/dir/index.html?page=1".to_owned());
+    assert!(digest.validate_request_uri(&same).is_ok());
+    let other = RequestUri::AbsolutePath("/dir/index.html?page=2".to_owned());
+    assert_eq!(Err(ValidationError::UriMismatch), digest.validate_request_uri(&other))
+}
+
+#[test]
+fn test_validate_nonce() {
+    let manager = HmacNonceManager::new(b"secret".to_vec(), Duration::from_secs(60));
+    let context = NonceContext::default();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Err(ValidationError::UnknownNonce),
+               digest.validate_nonce(&manager, &context));
+
+    digest.nonce = manager.issue(&context);
+    assert!(digest.validate_nonce(&manager, &context).is_ok())
+}
+
+struct StaleNonceManager;
+
+impl NonceManager for StaleNonceManager {
+    fn issue(&self, _: &NonceContext) -> String {
+        "stale".to_owned()
+    }
+
+    fn validate(&self, _: &str, _: &NonceContext) -> NonceStatus {
+        NonceStatus::Stale
+    }
+}
+
+#[test]
+fn test_validate_nonce_with_stale_nonce() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let result = digest.validate_nonce(&StaleNonceManager, &NonceContext::default());
+    assert_eq!(Err(ValidationError::StaleNonce), result);
+    let err = result.unwrap_err();
+    assert!(err.is_stale());
+    assert_eq!(StatusCode::Unauthorized, err.status_code())
+}
+
+#[test]
+fn test_validate_nonce_count() {
+    let guard = InMemoryReplayGuard::new(Duration::from_secs(60));
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert!(digest.validate_nonce_count(&guard).is_ok());
+    assert_eq!(Err(ValidationError::ReplayedNonceCount),
+               digest.validate_nonce_count(&guard))
+}
+
+#[test]
+fn test_validate_response_and_nonce_count() {
+    let guard = InMemoryReplayGuard::new(Duration::from_secs(60));
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let validate = |digest: &Digest| {
+        digest.validate_using_password(Method::Get, b"", "Circle Of Life".to_owned())
+    };
+    assert_eq!(Ok(()), digest.validate_response_and_nonce_count(&guard, &validate));
+    assert_eq!(Err(ValidationError::ReplayedNonceCount),
+               digest.validate_response_and_nonce_count(&guard, &validate))
+}
+
+#[test]
+fn test_forged_nonce_count_does_not_advance_nonce_count() {
+    let guard = InMemoryReplayGuard::new(Duration::from_secs(60));
+    let validate = |digest: &Digest| {
+        digest.validate_using_password(Method::Get, b"", "Circle Of Life".to_owned())
+    };
+    let mut forged = rfc2617_digest_header(HashAlgorithm::MD5);
+    forged.nonce_count = Some(NonceCount(0xffffffff));
+    forged.response = "00000000000000000000000000000000".to_owned();
+    assert_eq!(Err(ValidationError::WrongCredentials),
+               forged.validate_response_and_nonce_count(&guard, &validate));
+
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(Ok(()), digest.validate_response_and_nonce_count(&guard, &validate))
+}
+
+#[test]
+fn test_validate_nonce_count_sans_qop() {
+    let guard = InMemoryReplayGuard::new(Duration::from_secs(60));
+    let digest = rfc2069_a1_digest_header();
+    assert!(digest.validate_nonce_count(&guard).is_ok());
+    assert!(digest.validate_nonce_count(&guard).is_ok())
+}
+
+#[test]
+fn test_validate_nonce_count_sans_nonce_count() {
+    let guard = InMemoryReplayGuard::new(Duration::from_secs(60));
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.nonce_count = None;
+    assert_eq!(Err(ValidationError::Malformed(Error::MissingParameter("nc"))),
+               digest.validate_nonce_count(&guard))
 }
@@ -18,6 +18,8 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
+//! `Digest` fixtures and assertions that are shared by the tests of several modules.
+
 #![allow(dead_code)]
 
 use hyper::header::{Authorization, Header, Headers};
@@ -26,25 +28,30 @@ use parsing::test_helper;
 use super::{Digest, Username};
 use super::super::types::{HashAlgorithm, NonceCount, Qop};
 
+/// Asserts that `data` parses into the expected `Authorization` header.
 pub fn assert_parsed_header_equal(expected: Authorization<Digest>, data: &str) {
     test_helper::assert_parsed_header_equal(expected, data)
 }
 
+/// Asserts that `data` cannot be parsed into an `Authorization` header.
 pub fn assert_header_parsing_error(data: &str) {
     test_helper::assert_header_parsing_error::<Authorization<Digest>>(data)
 }
 
+/// Asserts that the `Authorization` header serializes into `actual`.
 pub fn assert_serialized_header_equal(digest: Digest, actual: &str) {
     let mut headers = Headers::new();
     headers.set(Authorization(digest));
     assert_eq!(headers.to_string(), format!("{}\r\n", actual))
 }
 
+/// Parses an `Authorization` header, panicking if it is malformed.
 pub fn parse_digest_header(data: &str) -> Authorization<Digest> {
     let bytestring = data.to_owned().into_bytes();
     Header::parse_header(&[bytestring][..]).expect("Could not parse digest header")
 }
 
+/// The username of the RFC 2069 and RFC 2617 examples.
 pub fn rfc2069_username() -> Username {
     Username::Plain("Mufasa".to_owned())
 }
@@ -66,17 +73,21 @@ fn rfc2069_digest_header(realm: &str) -> Digest {
         opaque: None,
         charset: None,
         userhash: false,
+        extensions: Digest::new_extensions(),
     }
 }
 
+/// The RFC 2069 example, with the realm that its A1 value is generated with.
 pub fn rfc2069_a1_digest_header() -> Digest {
     rfc2069_digest_header("testrealm@host.com")
 }
 
+/// The RFC 2069 example, with the realm that its A2 value is generated with.
 pub fn rfc2069_a2_digest_header() -> Digest {
     rfc2069_digest_header("myhost@testrealm.com")
 }
 
+/// Cf. https://tools.ietf.org/html/rfc2617#section-3.5
 pub fn rfc2617_digest_header(algorithm: HashAlgorithm) -> Digest {
     Digest {
         username: rfc2069_username(),
@@ -91,15 +102,17 @@ pub fn rfc2617_digest_header(algorithm: HashAlgorithm) -> Digest {
         opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".to_owned()),
         charset: None,
         userhash: false,
+        extensions: Digest::new_extensions(),
     }
 }
 
+/// The username of the RFC 7616 SHA-512/256 example, as an extended value.
 pub fn rfc7616_username() -> Username {
     let result = parse_extended_value("UTF-8''J%C3%A4s%C3%B8n%20Doe");
     Username::Encoded(result.expect("Could not parse extended value"))
 }
 
-// See: RFC 7616, Section 3.9.1
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.1
 pub fn rfc7616_digest_header(algorithm: HashAlgorithm, response: &str) -> Digest {
     Digest {
         username: rfc2069_username(),
@@ -114,9 +127,14 @@ pub fn rfc7616_digest_header(algorithm: HashAlgorithm, response: &str) -> Digest
         opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
         charset: None,
         userhash: false,
+        extensions: Digest::new_extensions(),
     }
 }
 
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.2
+///
+/// The `response` in the RFC was generated by truncating SHA-512 instead of with SHA-512/256 (cf.
+/// RFC 7616 errata), so this is the `response` that SHA-512/256 generates.
 pub fn rfc7616_sha512_256_header(username: String, userhash: bool) -> Digest {
     use hyper::header::Charset;
 
@@ -125,7 +143,7 @@ pub fn rfc7616_sha512_256_header(username: String, userhash: bool) -> Digest {
         realm: "api@example.org".to_owned(),
         nonce: "5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK".to_owned(),
         nonce_count: Some(NonceCount(1)),
-        response: "ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f607a79dd".to_owned(),
+        response: "3798d4131c277846293534c3edc11bd8a5e4cdcbff78b05db9d95eeb1cec68a5".to_owned(),
         request_uri: "/doe.json".to_owned(),
         algorithm: HashAlgorithm::SHA512256,
         qop: Some(Qop::Auth),
@@ -133,5 +151,6 @@ pub fn rfc7616_sha512_256_header(username: String, userhash: bool) -> Digest {
         opaque: Some("HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS".to_owned()),
         charset: Some(Charset::Ext("UTF-8".to_owned())),
         userhash: userhash,
+        extensions: Digest::new_extensions(),
     }
 }
@@ -0,0 +1,264 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! Errors that can occur when parsing or generating authentication headers.
+
+use hyper::Error as HyperError;
+use hyper::status::StatusCode;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+/// The reasons why an authentication header could not be parsed or used.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// A required parameter, such as `realm` or `nonce`, was not specified.
+    MissingParameter(&'static str),
+    /// A parameter has a value that cannot be parsed, such as an `nc` that is not eight
+    /// hexadecimal digits.
+    MalformedParameter(&'static str),
+    /// The parameters are not a comma-separated list of `name=value` pairs whose values are
+    /// tokens or quoted-strings.
+    MalformedParameters,
+    /// A parameter was specified more than once.
+    DuplicateParameter(String),
+    /// An extension parameter has the name of a standard parameter of the header.
+    ReservedParameter(String),
+    /// The `algorithm` parameter names a hash algorithm that is neither built in nor registered,
+    /// so digests cannot be generated with it.
+    UnsupportedAlgorithm(String),
+    /// The `charset` parameter names a character set other than `UTF-8`.
+    UnsupportedCharset(String),
+    /// Two parameters that cannot be used together were both specified, such as `username` and
+    /// `username*`.
+    ConflictingParameters(&'static str, &'static str),
+    /// A `-sess` algorithm was used without a client nonce (`cnonce`).
+    MissingClientNonce,
+    /// The `username` is a userhash, so the username that it was generated from is needed.
+    MissingUsername,
+    /// The header does not use the expected auth-scheme.
+    UnexpectedScheme(&'static str),
+    /// The `Basic` credentials are not a base64-encoded `user-id:password` pair.
+    MalformedCredentials,
+    /// The `WWW-Authenticate` header does not contain any challenges.
+    MissingChallenge,
+    /// A username or password contains a character that its PRECIS profile does not allow, or a
+    /// `Basic` user-id contains a colon.
+    DisallowedCharacter(char),
+    /// A username or password is empty after it was prepared with its PRECIS profile.
+    EmptyCredential,
+    /// A username that contains right-to-left characters does not satisfy the Bidi Rule of
+    /// [RFC 5893](https://tools.ietf.org/html/rfc5893#section-2).
+    BidiRuleViolation,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::MissingParameter(name) => write!(f, "Missing parameter: {}", name),
+            Error::MalformedParameter(name) => write!(f, "Malformed parameter: {}", name),
+            Error::MalformedParameters => write!(f, "Malformed parameters"),
+            Error::DuplicateParameter(ref name) => write!(f, "Duplicate parameter: {}", name),
+            Error::ReservedParameter(ref name) => write!(f, "Reserved parameter: {}", name),
+            Error::UnsupportedAlgorithm(ref algorithm) => {
+                write!(f, "Unsupported algorithm: {}", algorithm)
+            }
+            Error::UnsupportedCharset(ref charset) => write!(f, "Unsupported charset: {}", charset),
+            Error::ConflictingParameters(first, second) => {
+                write!(f, "Conflicting parameters: {} and {}", first, second)
+            }
+            Error::MissingClientNonce => write!(f, "Session algorithms require a cnonce"),
+            Error::MissingUsername => write!(f, "The username is a userhash"),
+            Error::UnexpectedScheme(scheme) => write!(f, "Expected the {} scheme", scheme),
+            Error::MalformedCredentials => write!(f, "Malformed credentials"),
+            Error::MissingChallenge => write!(f, "Missing challenge"),
+            Error::DisallowedCharacter(c) => write!(f, "Disallowed character: {:?}", c),
+            Error::EmptyCredential => write!(f, "Empty username or password"),
+            Error::BidiRuleViolation => write!(f, "Violates the Bidi Rule"),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::MissingParameter(_) => "A required parameter is missing",
+            Error::MalformedParameter(_) => "A parameter value is malformed",
+            Error::MalformedParameters => "The parameters are malformed",
+            Error::DuplicateParameter(_) => "A parameter was specified more than once",
+            Error::ReservedParameter(_) => "An extension parameter has a reserved name",
+            Error::UnsupportedAlgorithm(_) => "The hash algorithm is not supported",
+            Error::UnsupportedCharset(_) => "The character set is not supported",
+            Error::ConflictingParameters(_, _) => "Parameters that conflict were both specified",
+            Error::MissingClientNonce => "A session algorithm requires a client nonce",
+            Error::MissingUsername => "The username that a userhash was generated from is needed",
+            Error::UnexpectedScheme(_) => "The auth-scheme is not the expected one",
+            Error::MalformedCredentials => "The credentials are malformed",
+            Error::MissingChallenge => "No challenges were specified",
+            Error::DisallowedCharacter(_) => "A character is not allowed by the PRECIS profile",
+            Error::EmptyCredential => "The username or password is empty",
+            Error::BidiRuleViolation => "The username does not satisfy the Bidi Rule",
+        }
+    }
+}
+
+impl From<Error> for HyperError {
+    fn from(_: Error) -> HyperError {
+        HyperError::Header
+    }
+}
+
+/// The reasons why a server rejected the `Digest` credentials that a client sent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    /// The `response` does not match the one generated from the user's credentials.
+    WrongCredentials,
+    /// The userhash does not match the user's username.
+    UserhashMismatch,
+    /// The user is not known to the `CredentialStore`.
+    UnknownUser,
+    /// The `realm` is not the realm of the protection space.
+    RealmMismatch,
+    /// The `uri` does not identify the resource in the request line.
+    UriMismatch,
+    /// The nonce was issued by the server, but is no longer valid. The client should retry with a
+    /// new nonce.
+    StaleNonce,
+    /// The nonce was not issued by the server.
+    UnknownNonce,
+    /// The nonce count was already used with the nonce.
+    ReplayedNonceCount,
+    /// The `algorithm` is not supported by the server. The server should respond with challenges
+    /// for the algorithms that it supports.
+    UnsupportedAlgorithm(String),
+    /// The parameters cannot be used to generate a `response`.
+    Malformed(Error),
+}
+
+impl ValidationError {
+    /// The status code for the server's response, as described in
+    /// [RFC 7616, section 3.4.6](https://tools.ietf.org/html/rfc7616#section-3.4.6): `400 Bad
+    /// Request` for malformed parameters or a mismatched `uri`, otherwise `401 Unauthorized`.
+    pub fn status_code(&self) -> StatusCode {
+        match *self {
+            ValidationError::UriMismatch |
+            ValidationError::Malformed(_) => StatusCode::BadRequest,
+            _ => StatusCode::Unauthorized,
+        }
+    }
+
+    /// Whether the server should send a new challenge with `stale=true`, so that the client can
+    /// retry without asking the user for their credentials again.
+    pub fn is_stale(&self) -> bool {
+        *self == ValidationError::StaleNonce
+    }
+
+    fn message(&self) -> &'static str {
+        match *self {
+            ValidationError::WrongCredentials => "The credentials are wrong",
+            ValidationError::UserhashMismatch => "The userhash does not match the username",
+            ValidationError::UnknownUser => "The user is unknown",
+            ValidationError::RealmMismatch => "The realm does not match",
+            ValidationError::UriMismatch => "The URI does not match the request",
+            ValidationError::StaleNonce => "The nonce is stale",
+            ValidationError::UnknownNonce => "The nonce is unknown",
+            ValidationError::ReplayedNonceCount => "The nonce count was already used",
+            ValidationError::UnsupportedAlgorithm(_) => "The hash algorithm is not supported",
+            ValidationError::Malformed(_) => "The digest is malformed",
+        }
+    }
+}
+
+impl From<Error> for ValidationError {
+    fn from(err: Error) -> ValidationError {
+        match err {
+            Error::UnsupportedAlgorithm(algorithm) => {
+                ValidationError::UnsupportedAlgorithm(algorithm)
+            }
+            err => ValidationError::Malformed(err),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::Malformed(ref err) => write!(f, "Malformed digest: {}", err),
+            ValidationError::UnsupportedAlgorithm(ref algorithm) => {
+                write!(f, "Unsupported algorithm: {}", algorithm)
+            }
+            _ => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl StdError for ValidationError {
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
+
+/// The reasons why an `htdigest` password file could not be loaded. Lines are numbered from 1.
+#[derive(Debug)]
+pub enum HtdigestError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The line is not of the form `username:realm:HA1`.
+    MalformedLine(usize),
+    /// The HA1 on the line is not an MD5 digest in hexadecimal.
+    MalformedHash(usize),
+    /// The line has the same username and realm as an earlier line.
+    DuplicateEntry(usize),
+}
+
+impl HtdigestError {
+    fn message(&self) -> &'static str {
+        match *self {
+            HtdigestError::Io(_) => "The password file could not be read",
+            HtdigestError::MalformedLine(_) => "The line is not of the form username:realm:HA1",
+            HtdigestError::MalformedHash(_) => "The HA1 is not a hexadecimal MD5 digest",
+            HtdigestError::DuplicateEntry(_) => "The username and realm were already specified",
+        }
+    }
+}
+
+impl From<io::Error> for HtdigestError {
+    fn from(err: io::Error) -> HtdigestError {
+        HtdigestError::Io(err)
+    }
+}
+
+impl fmt::Display for HtdigestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HtdigestError::Io(ref err) => write!(f, "{}: {}", self.message(), err),
+            HtdigestError::MalformedLine(line) |
+            HtdigestError::MalformedHash(line) |
+            HtdigestError::DuplicateEntry(line) => write!(f, "Line {}: {}", line, self.message()),
+        }
+    }
+}
+
+impl StdError for HtdigestError {
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! Reads `htdigest`-style password files, as written by Apache's `htdigest` tool (or the
+//! `ghdigest` example).
+//!
+//! Each line of the file has the form `username:realm:HA1`, where `HA1` is the simple hashed A1
+//! for the `MD5` algorithm (see `Digest::simple_hashed_a1`). Blank lines, and lines that start
+//! with `#`, are ignored.
+
+use credentials::{self, Credential, CredentialStore};
+use digest::{Digest, Username};
+use error::HtdigestError;
+use std::collections::HashMap;
+use std::fs::{File, metadata};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::SystemTime;
+use super::types::HashAlgorithm;
+
+mod test;
+
+const HASHED_A1_LENGTH: usize = 32;
+
+/// The hashed A1 values in an `htdigest` file, keyed by username and realm.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Htdigest {
+    entries: HashMap<(String, String), String>,
+    userhashes: HashMap<(String, String), String>,
+}
+
+fn is_hashed_a1(value: &str) -> bool {
+    value.len() == HASHED_A1_LENGTH && value.chars().all(|c| c.is_digit(16))
+}
+
+impl Htdigest {
+    /// Parses the contents of an `htdigest` file.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Htdigest, HtdigestError> {
+        let mut entries: HashMap<(String, String), String> = HashMap::new();
+        let mut userhashes: HashMap<(String, String), String> = HashMap::new();
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let line_number = index + 1;
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() != 3 || fields[0].is_empty() {
+                return Err(HtdigestError::MalformedLine(line_number));
+            }
+            if !is_hashed_a1(fields[2]) {
+                return Err(HtdigestError::MalformedHash(line_number));
+            }
+            let key = (fields[0].to_owned(), fields[1].to_owned());
+            if entries.contains_key(&key) {
+                return Err(HtdigestError::DuplicateEntry(line_number));
+            }
+            let userhash = credentials::userhash(fields[0], fields[1], &HashAlgorithm::MD5)
+                .expect("MD5 is a built-in algorithm");
+            userhashes.insert((fields[1].to_owned(), userhash), fields[0].to_owned());
+            entries.insert(key, fields[2].to_lowercase());
+        }
+
+        Ok(Htdigest {
+            entries: entries,
+            userhashes: userhashes,
+        })
+    }
+
+    /// The simple hashed A1 for the given username and realm, if the user exists.
+    pub fn hashed_a1(&self, username: &str, realm: &str) -> Option<String> {
+        self.entries.get(&(username.to_owned(), realm.to_owned())).cloned()
+    }
+
+    /// The hashed A1 for the user that sent the `Digest` header, which can be passed to
+    /// `Digest::validate_using_hashed_a1`.
+    ///
+    /// Returns `None` if the user does not exist, if the header uses a userhash, or if the
+    /// header's algorithm is not `MD5` or `MD5-sess` (which are the only algorithms that an
+    /// `htdigest` file can be used with). Also returns `None` if the algorithm is `MD5-sess` and
+    /// the header does not have a client nonce.
+    pub fn hashed_a1_for_digest(&self, digest: &Digest) -> Option<String> {
+        match digest.algorithm {
+            HashAlgorithm::MD5 |
+            HashAlgorithm::MD5Session => {}
+            _ => return None,
+        }
+        if digest.userhash {
+            return None;
+        }
+        let username = match digest.username {
+            Username::Plain(ref username) => username.clone(),
+            Username::Encoded(ref encoded) => {
+                match String::from_utf8(encoded.value.clone()) {
+                    Ok(username) => username,
+                    Err(_) => return None,
+                }
+            }
+        };
+        match self.hashed_a1(&username, &digest.realm) {
+            Some(hashed_a1) => digest.hashed_a1_from_simple(hashed_a1).ok(),
+            None => None,
+        }
+    }
+
+    /// The number of users, across all realms.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no users.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The hashed A1 values in an `htdigest` file can only be used with the `MD5` and `MD5-sess`
+/// algorithms, so are only returned for those.
+impl CredentialStore for Htdigest {
+    fn credential(&self,
+                  username: &str,
+                  realm: &str,
+                  algorithm: &HashAlgorithm)
+                  -> Option<Credential> {
+        match *algorithm {
+            HashAlgorithm::MD5 |
+            HashAlgorithm::MD5Session => self.hashed_a1(username, realm).map(Credential::HashedA1),
+            _ => None,
+        }
+    }
+
+    fn username_for_userhash(&self,
+                             userhash: &str,
+                             realm: &str,
+                             algorithm: &HashAlgorithm)
+                             -> Option<String> {
+        match *algorithm {
+            HashAlgorithm::MD5 |
+            HashAlgorithm::MD5Session => {
+                self.userhashes.get(&(realm.to_owned(), userhash.to_lowercase())).cloned()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Htdigest {
+    type Err = HtdigestError;
+
+    fn from_str(s: &str) -> Result<Htdigest, HtdigestError> {
+        Htdigest::from_reader(s.as_bytes())
+    }
+}
+
+/// An `htdigest` file that can be reloaded when it changes, and that can be shared between
+/// threads.
+#[derive(Debug)]
+pub struct HtdigestFile {
+    path: PathBuf,
+    modified: RwLock<Option<SystemTime>>,
+    htdigest: RwLock<Htdigest>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl HtdigestFile {
+    /// Loads an `htdigest` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<HtdigestFile, HtdigestError> {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path);
+        let htdigest = Htdigest::from_reader(File::open(&path)?)?;
+        Ok(HtdigestFile {
+            path: path,
+            modified: RwLock::new(modified),
+            htdigest: RwLock::new(htdigest),
+        })
+    }
+
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A copy of the currently loaded users.
+    pub fn htdigest(&self) -> Htdigest {
+        self.htdigest.read().expect("htdigest file is poisoned").clone()
+    }
+
+    /// Reloads the file. If it cannot be loaded, the previously loaded users are kept.
+    pub fn reload(&self) -> Result<(), HtdigestError> {
+        let modified = modified_time(&self.path);
+        let htdigest = Htdigest::from_reader(File::open(&self.path)?)?;
+        *self.htdigest.write().expect("htdigest file is poisoned") = htdigest;
+        *self.modified.write().expect("htdigest file is poisoned") = modified;
+        Ok(())
+    }
+
+    /// Reloads the file if its modification time changed since it was last loaded. Returns
+    /// whether it was reloaded.
+    pub fn reload_if_modified(&self) -> Result<bool, HtdigestError> {
+        let modified = modified_time(&self.path);
+        let loaded = *self.modified.read().expect("htdigest file is poisoned");
+        if modified.is_some() && modified == loaded {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// The simple hashed A1 for the given username and realm, if the user exists.
+    pub fn hashed_a1(&self, username: &str, realm: &str) -> Option<String> {
+        self.htdigest.read().expect("htdigest file is poisoned").hashed_a1(username, realm)
+    }
+
+    /// The hashed A1 for the user that sent the `Digest` header. See
+    /// `Htdigest::hashed_a1_for_digest`.
+    pub fn hashed_a1_for_digest(&self, digest: &Digest) -> Option<String> {
+        self.htdigest.read().expect("htdigest file is poisoned").hashed_a1_for_digest(digest)
+    }
+}
+
+impl CredentialStore for HtdigestFile {
+    fn credential(&self,
+                  username: &str,
+                  realm: &str,
+                  algorithm: &HashAlgorithm)
+                  -> Option<Credential> {
+        self.htdigest
+            .read()
+            .expect("htdigest file is poisoned")
+            .credential(username, realm, algorithm)
+    }
+
+    fn username_for_userhash(&self,
+                             userhash: &str,
+                             realm: &str,
+                             algorithm: &HashAlgorithm)
+                             -> Option<String> {
+        self.htdigest
+            .read()
+            .expect("htdigest file is poisoned")
+            .username_for_userhash(userhash, realm, algorithm)
+    }
+}
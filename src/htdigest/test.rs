@@ -0,0 +1,179 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use digest::test_helper::rfc2617_digest_header;
+use error::HtdigestError;
+use hyper::method::Method;
+use std::env;
+use std::fs::{File, remove_file};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::{Htdigest, HtdigestFile};
+use super::super::types::HashAlgorithm;
+
+const PASSWORD_FILE: &'static str = "# Generated by ghdigest\n\
+                                     Mufasa:testrealm@host.com:939e7578ed9e3c518a452acee763bce9\n\
+                                     \n\
+                                     Mufasa:other realm:0123456789ABCDEF0123456789abcdef\n";
+
+/// A password file in the temporary directory, which is removed when it goes out of scope. Its name
+/// contains the name of the test and the current time, so that tests can run in parallel.
+struct TempPasswordFile {
+    path: PathBuf,
+}
+
+impl TempPasswordFile {
+    fn new(name: &str, contents: &str) -> TempPasswordFile {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Could not get current time");
+        let mut path = env::temp_dir();
+        path.push(format!("guardhaus-{}-{}-{}.htdigest", name, now.as_secs(), now.subsec_nanos()));
+        let file = TempPasswordFile { path: path };
+        file.write(contents);
+        file
+    }
+
+    fn write(&self, contents: &str) {
+        let mut file = File::create(&self.path).expect("Could not create password file");
+        file.write_all(contents.as_bytes()).expect("Could not write password file");
+    }
+}
+
+impl Drop for TempPasswordFile {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+#[test]
+fn test_parse() {
+    let htdigest = Htdigest::from_str(PASSWORD_FILE).expect("Could not parse password file");
+    assert_eq!(2, htdigest.len());
+    assert_eq!(Some("939e7578ed9e3c518a452acee763bce9".to_owned()),
+               htdigest.hashed_a1("Mufasa", "testrealm@host.com"));
+    assert_eq!(Some("0123456789abcdef0123456789abcdef".to_owned()),
+               htdigest.hashed_a1("Mufasa", "other realm"));
+    assert_eq!(None, htdigest.hashed_a1("Simba", "testrealm@host.com"))
+}
+
+#[test]
+fn test_parse_empty() {
+    let htdigest = Htdigest::from_str("# No users\n\n").expect("Could not parse password file");
+    assert!(htdigest.is_empty())
+}
+
+#[test]
+fn test_parse_malformed_line() {
+    match Htdigest::from_str("# Comment\nMufasa:testrealm@host.com\n") {
+        Err(HtdigestError::MalformedLine(2)) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    match Htdigest::from_str(":realm:939e7578ed9e3c518a452acee763bce9") {
+        Err(HtdigestError::MalformedLine(1)) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_parse_malformed_hash() {
+    match Htdigest::from_str("Mufasa:testrealm@host.com:Circle Of Life") {
+        Err(HtdigestError::MalformedHash(1)) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_parse_duplicate_entry() {
+    let contents = format!("{}Mufasa:testrealm@host.com:0123456789abcdef0123456789abcdef\n",
+                           PASSWORD_FILE);
+    match Htdigest::from_str(&contents) {
+        Err(HtdigestError::DuplicateEntry(5)) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_hashed_a1_for_digest() {
+    let htdigest = Htdigest::from_str(PASSWORD_FILE).expect("Could not parse password file");
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let hashed_a1 = htdigest.hashed_a1_for_digest(&digest).expect("Could not find user");
+    assert!(digest.validate_using_hashed_a1(Method::Get, b"", hashed_a1).is_ok())
+}
+
+#[test]
+fn test_hashed_a1_for_digest_with_session_algorithm() {
+    let htdigest = Htdigest::from_str(PASSWORD_FILE).expect("Could not parse password file");
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.algorithm = HashAlgorithm::MD5Session;
+    let password = "Circle Of Life".to_owned();
+    digest.response = digest.using_password(Method::Get, b"", password)
+        .expect("Could not generate response");
+    let hashed_a1 = htdigest.hashed_a1_for_digest(&digest).expect("Could not find user");
+    assert!(digest.validate_using_hashed_a1(Method::Get, b"", hashed_a1).is_ok())
+}
+
+#[test]
+fn test_hashed_a1_for_digest_with_unsupported_algorithm() {
+    let htdigest = Htdigest::from_str(PASSWORD_FILE).expect("Could not parse password file");
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.algorithm = HashAlgorithm::SHA256;
+    assert_eq!(None, htdigest.hashed_a1_for_digest(&digest))
+}
+
+#[test]
+fn test_hashed_a1_for_digest_with_userhash() {
+    let htdigest = Htdigest::from_str(PASSWORD_FILE).expect("Could not parse password file");
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.userhash = true;
+    assert_eq!(None, htdigest.hashed_a1_for_digest(&digest))
+}
+
+#[test]
+fn test_file_reload() {
+    let password_file = TempPasswordFile::new("reload", PASSWORD_FILE);
+    let file = HtdigestFile::open(&password_file.path).expect("Could not open password file");
+    assert_eq!(2, file.htdigest().len());
+    assert!(!file.reload_if_modified().expect("Could not check password file"));
+
+    password_file.write("Simba:testrealm@host.com:0123456789abcdef0123456789abcdef\n");
+    file.reload().expect("Could not reload password file");
+    assert_eq!(None, file.hashed_a1("Mufasa", "testrealm@host.com"));
+    assert!(file.hashed_a1("Simba", "testrealm@host.com").is_some());
+
+    remove_file(&password_file.path).expect("Could not remove password file");
+    match file.reload_if_modified() {
+        Err(HtdigestError::Io(_)) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    assert_eq!(1, file.htdigest().len())
+}
+
+#[test]
+fn test_file_with_malformed_line() {
+    let password_file = TempPasswordFile::new("malformed-line", "Mufasa\n");
+    match HtdigestFile::open(&password_file.path) {
+        Err(HtdigestError::MalformedLine(1)) => {}
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
@@ -23,16 +23,47 @@
 extern crate crypto_hash;
 extern crate hex;
 extern crate hyper;
+extern crate rand;
+extern crate rustc_serialize;
 extern crate unicase;
+extern crate unicode_bidi;
+extern crate unicode_categories;
+extern crate unicode_normalization;
 extern crate url;
 
+#[warn(missing_docs)]
+pub mod algorithm;
 #[warn(missing_docs)]
 pub mod authentication_info;
 #[warn(missing_docs)]
+pub mod basic;
+#[warn(missing_docs)]
+pub mod client;
+#[warn(missing_docs)]
+mod constant_time;
+#[warn(missing_docs)]
+pub mod credentials;
+#[warn(missing_docs)]
 pub mod digest;
 #[warn(missing_docs)]
+pub mod error;
+#[warn(missing_docs)]
+pub mod htdigest;
+#[warn(missing_docs)]
+pub mod nonce;
+#[warn(missing_docs)]
 mod parsing;
 #[warn(missing_docs)]
+pub mod precis;
+#[warn(missing_docs)]
+pub mod proxy;
+#[warn(missing_docs)]
+pub mod replay;
+#[warn(missing_docs)]
+mod sha512_256;
+#[warn(missing_docs)]
 pub mod types;
 #[warn(missing_docs)]
+mod uri;
+#[warn(missing_docs)]
 pub mod www_authenticate;
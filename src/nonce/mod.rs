@@ -0,0 +1,240 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Server-side generation and validation of `nonce` values.
+//!
+//! The suggested nonce format is described in
+//! [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3): a timestamp and a
+//! hash of the timestamp, the resource's `ETag`, and private data known only to the server.
+
+use constant_time;
+use crypto_hash::{Algorithm, digest};
+use hex::ToHex;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+mod test;
+
+const HMAC_BLOCK_SIZE: usize = 64;
+const TIMESTAMP_LENGTH: usize = 24;
+const SIGNATURE_LENGTH: usize = 64;
+
+/// The request-specific data that a nonce is bound to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NonceContext {
+    /// The IP address of the client that the nonce is issued to.
+    pub client_address: Option<IpAddr>,
+    /// The `ETag` of the requested resource.
+    pub etag: Option<String>,
+}
+
+/// The state of a nonce sent by a client.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NonceStatus {
+    /// The nonce was issued by the server and has not expired.
+    Valid,
+    /// The nonce was issued by the server, but has expired. The server should send a new challenge
+    /// with `stale=true`.
+    Stale,
+    /// The nonce was not issued by the server, or was issued for a different context.
+    Invalid,
+}
+
+/// Issues nonces for `WWW-Authenticate` challenges, and validates nonces sent back by clients.
+pub trait NonceManager {
+    /// Generates a new nonce for the given context.
+    fn issue(&self, context: &NonceContext) -> String;
+
+    /// Determines whether `nonce` was issued by this manager for the given context, and whether
+    /// it has expired.
+    fn validate(&self, nonce: &str, context: &NonceContext) -> NonceStatus;
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut padded_key = if key.len() > HMAC_BLOCK_SIZE {
+        digest(Algorithm::SHA256, key.to_vec())
+    } else {
+        key.to_vec()
+    };
+    padded_key.resize(HMAC_BLOCK_SIZE, 0);
+
+    let mut inner: Vec<u8> = padded_key.iter().map(|byte| byte ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = padded_key.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.append(&mut digest(Algorithm::SHA256, inner));
+    digest(Algorithm::SHA256, outer)
+}
+
+/// Appends an optional field to a message, prefixed with whether it is present and its length, so
+/// that the fields of different messages cannot run into each other.
+fn append_field(message: &mut Vec<u8>, field: Option<&[u8]>) {
+    match field {
+        Some(value) => {
+            let length = value.len() as u64;
+            message.push(1);
+            for shift in (0..8).rev() {
+                message.push((length >> (shift * 8)) as u8);
+            }
+            message.extend_from_slice(value);
+        }
+        None => message.push(0),
+    }
+}
+
+fn since_epoch() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0))
+}
+
+/// A stateless `NonceManager`.
+///
+/// Each nonce consists of the time it was issued and an HMAC-SHA-256 signature of that time and
+/// the `NonceContext`, keyed with a server secret. Any server that shares the secret can validate
+/// the nonce.
+#[derive(Clone, Debug)]
+pub struct HmacNonceManager {
+    secret: Vec<u8>,
+    lifetime: Duration,
+}
+
+impl HmacNonceManager {
+    /// Creates a nonce manager, given the server secret and how long an issued nonce is valid.
+    pub fn new(secret: Vec<u8>, lifetime: Duration) -> HmacNonceManager {
+        HmacNonceManager {
+            secret: secret,
+            lifetime: lifetime,
+        }
+    }
+
+    fn signature(&self, timestamp: &str, context: &NonceContext) -> String {
+        let client_address = context.client_address.map(|address| address.to_string());
+        let mut message = timestamp.as_bytes().to_vec();
+        append_field(&mut message, client_address.as_ref().map(|address| address.as_bytes()));
+        append_field(&mut message, context.etag.as_ref().map(|etag| etag.as_bytes()));
+        hmac_sha256(&self.secret[..], &message[..]).to_hex()
+    }
+
+    fn issue_at(&self, issued: Duration, context: &NonceContext) -> String {
+        let timestamp = format!("{:016x}{:08x}", issued.as_secs(), issued.subsec_nanos());
+        let signature = self.signature(&timestamp, context);
+        format!("{}{}", timestamp, signature)
+    }
+
+    fn validate_at(&self, nonce: &str, context: &NonceContext, now: Duration) -> NonceStatus {
+        if nonce.len() != TIMESTAMP_LENGTH + SIGNATURE_LENGTH ||
+           !nonce.chars().all(|c| c.is_digit(16)) {
+            return NonceStatus::Invalid;
+        }
+
+        let (timestamp, signature) = nonce.split_at(TIMESTAMP_LENGTH);
+        if !constant_time::str_eq(signature, &self.signature(timestamp, context)) {
+            return NonceStatus::Invalid;
+        }
+
+        match u64::from_str_radix(&timestamp[..16], 16) {
+            Ok(issued) if now.as_secs().saturating_sub(issued) > self.lifetime.as_secs() => {
+                NonceStatus::Stale
+            }
+            Ok(_) => NonceStatus::Valid,
+            Err(_) => NonceStatus::Invalid,
+        }
+    }
+}
+
+impl NonceManager for HmacNonceManager {
+    fn issue(&self, context: &NonceContext) -> String {
+        self.issue_at(since_epoch(), context)
+    }
+
+    fn validate(&self, nonce: &str, context: &NonceContext) -> NonceStatus {
+        self.validate_at(nonce, context, since_epoch())
+    }
+}
+
+/// A `NonceManager` that keeps track of every nonce it has issued, in memory.
+///
+/// Nonces are generated by an `HmacNonceManager`. Unlike that manager, a nonce is only valid if it
+/// was issued by this instance and has not been revoked.
+///
+/// Expired nonces are not forgotten automatically, so the manager grows without bound unless
+/// `purge_expired` is called periodically.
+#[derive(Debug)]
+pub struct InMemoryNonceManager {
+    issuer: HmacNonceManager,
+    issued: Mutex<HashMap<String, Duration>>,
+}
+
+impl InMemoryNonceManager {
+    /// Creates a nonce manager, given the server secret and how long an issued nonce is valid.
+    pub fn new(secret: Vec<u8>, lifetime: Duration) -> InMemoryNonceManager {
+        InMemoryNonceManager {
+            issuer: HmacNonceManager::new(secret, lifetime),
+            issued: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Invalidates a previously issued nonce. Returns whether the nonce was known.
+    pub fn revoke(&self, nonce: &str) -> bool {
+        self.issued.lock().expect("Nonce store is poisoned").remove(nonce).is_some()
+    }
+
+    /// Forgets about every issued nonce that has expired. This should be called periodically
+    /// (for example, from a timer), since nothing else removes expired nonces.
+    pub fn purge_expired(&self) {
+        let now = since_epoch();
+        let lifetime = self.issuer.lifetime.as_secs();
+        let mut issued = self.issued.lock().expect("Nonce store is poisoned");
+        let expired: Vec<String> = issued.iter()
+            .filter(|&(_, timestamp)| now.as_secs().saturating_sub(timestamp.as_secs()) > lifetime)
+            .map(|(nonce, _)| nonce.clone())
+            .collect();
+        for nonce in expired {
+            issued.remove(&nonce);
+        }
+    }
+
+    /// The number of issued nonces that are being tracked.
+    pub fn len(&self) -> usize {
+        self.issued.lock().expect("Nonce store is poisoned").len()
+    }
+
+    /// Whether no issued nonces are being tracked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl NonceManager for InMemoryNonceManager {
+    fn issue(&self, context: &NonceContext) -> String {
+        let issued = since_epoch();
+        let nonce = self.issuer.issue_at(issued, context);
+        self.issued.lock().expect("Nonce store is poisoned").insert(nonce.clone(), issued);
+        nonce
+    }
+
+    fn validate(&self, nonce: &str, context: &NonceContext) -> NonceStatus {
+        if self.issued.lock().expect("Nonce store is poisoned").contains_key(nonce) {
+            self.issuer.validate(nonce, context)
+        } else {
+            NonceStatus::Invalid
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use hex::ToHex;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use super::{HmacNonceManager, InMemoryNonceManager, NonceContext, NonceManager, NonceStatus,
+            hmac_sha256};
+
+fn context() -> NonceContext {
+    NonceContext {
+        client_address: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+        etag: Some("\"xyzzy\"".to_owned()),
+    }
+}
+
+fn manager() -> HmacNonceManager {
+    HmacNonceManager::new(b"server secret".to_vec(), Duration::from_secs(300))
+}
+
+/// Cf. https://tools.ietf.org/html/rfc4231#section-4.3
+#[test]
+fn test_hmac_sha256() {
+    let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+    assert_eq!(expected,
+               hmac_sha256(b"Jefe", b"what do ya want for nothing?").to_hex())
+}
+
+/// Cf. https://tools.ietf.org/html/rfc4231#section-4.7
+#[test]
+fn test_hmac_sha256_with_long_key() {
+    let key = vec![0xaa; 131];
+    let expected = "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54";
+    assert_eq!(expected,
+               hmac_sha256(&key[..], b"Test Using Larger Than Block-Size Key - Hash Key First")
+                   .to_hex())
+}
+
+#[test]
+fn test_issue_and_validate() {
+    let manager = manager();
+    let nonce = manager.issue(&context());
+    assert_eq!(NonceStatus::Valid, manager.validate(&nonce, &context()))
+}
+
+#[test]
+fn test_issued_nonces_are_unique() {
+    let manager = manager();
+    let first = manager.issue_at(Duration::new(1000, 1), &context());
+    let second = manager.issue_at(Duration::new(1000, 2), &context());
+    assert!(first != second)
+}
+
+#[test]
+fn test_validate_expired_nonce() {
+    let manager = manager();
+    let nonce = manager.issue_at(Duration::from_secs(1000), &context());
+    assert_eq!(NonceStatus::Valid,
+               manager.validate_at(&nonce, &context(), Duration::from_secs(1300)));
+    assert_eq!(NonceStatus::Stale,
+               manager.validate_at(&nonce, &context(), Duration::from_secs(1301)))
+}
+
+#[test]
+fn test_validate_nonce_with_different_context() {
+    let manager = manager();
+    let nonce = manager.issue(&context());
+    let mut other_client = context();
+    other_client.client_address = Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+    assert_eq!(NonceStatus::Invalid, manager.validate(&nonce, &other_client));
+
+    let mut other_etag = context();
+    other_etag.etag = None;
+    assert_eq!(NonceStatus::Invalid, manager.validate(&nonce, &other_etag))
+}
+
+#[test]
+fn test_validate_nonce_with_ambiguous_context() {
+    let manager = manager();
+    let context = NonceContext {
+        client_address: Some(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1))),
+        etag: Some("x".to_owned()),
+    };
+    let nonce = manager.issue(&context);
+    let other = NonceContext {
+        client_address: None,
+        etag: Some(":1:x".to_owned()),
+    };
+    assert_eq!(NonceStatus::Invalid, manager.validate(&nonce, &other));
+
+    let empty_etag = NonceContext {
+        client_address: None,
+        etag: Some(String::new()),
+    };
+    let nonce = manager.issue(&empty_etag);
+    assert_eq!(NonceStatus::Invalid,
+               manager.validate(&nonce, &NonceContext::default()))
+}
+
+#[test]
+fn test_validate_nonce_with_different_secret() {
+    let nonce = manager().issue(&context());
+    let other = HmacNonceManager::new(b"other secret".to_vec(), Duration::from_secs(300));
+    assert_eq!(NonceStatus::Invalid, other.validate(&nonce, &context()))
+}
+
+#[test]
+fn test_validate_malformed_nonce() {
+    let manager = manager();
+    assert_eq!(NonceStatus::Invalid,
+               manager.validate("dcd98b7102dd2f0e8b11d0f600bfb0c093", &context()));
+    let nonce = manager.issue(&context());
+    let tampered = format!("{}{}", "f", &nonce[1..]);
+    assert_eq!(NonceStatus::Invalid, manager.validate(&tampered, &context()));
+    let non_ascii = format!("{}é", &nonce[2..]);
+    assert_eq!(NonceStatus::Invalid, manager.validate(&non_ascii, &context()))
+}
+
+#[test]
+fn test_in_memory_issue_and_validate() {
+    let manager = InMemoryNonceManager::new(b"server secret".to_vec(), Duration::from_secs(300));
+    let nonce = manager.issue(&context());
+    assert_eq!(1, manager.len());
+    assert_eq!(NonceStatus::Valid, manager.validate(&nonce, &context()));
+    manager.purge_expired();
+    assert_eq!(1, manager.len())
+}
+
+#[test]
+fn test_in_memory_rejects_nonces_it_did_not_issue() {
+    let in_memory = InMemoryNonceManager::new(b"server secret".to_vec(), Duration::from_secs(300));
+    let nonce = manager().issue(&context());
+    assert_eq!(NonceStatus::Invalid, in_memory.validate(&nonce, &context()))
+}
+
+#[test]
+fn test_in_memory_revoke() {
+    let manager = InMemoryNonceManager::new(b"server secret".to_vec(), Duration::from_secs(300));
+    let nonce = manager.issue(&context());
+    assert!(manager.revoke(&nonce));
+    assert!(!manager.revoke(&nonce));
+    assert!(manager.is_empty());
+    assert_eq!(NonceStatus::Invalid, manager.validate(&nonce, &context()))
+}
+
+#[test]
+fn test_in_memory_purge_expired() {
+    let manager = InMemoryNonceManager::new(b"server secret".to_vec(), Duration::from_secs(0));
+    manager.issued.lock().unwrap().insert("expired".to_owned(), Duration::from_secs(1000));
+    manager.purge_expired();
+    assert!(manager.is_empty())
+}
@@ -20,80 +20,333 @@
 
 //! Utility functions to parse headers.
 
+use error::Error;
+use hyper::header::Charset;
 use std::collections::HashMap;
 use std::str;
+use types::{AuthParam, ExtensionParameters};
 use unicase::UniCase;
-use url::percent_encoding::percent_decode;
 
+mod test;
 pub mod test_helper;
 
-/// Append a header parameter to a serialized header.
+/// Whether the character can be part of a `token`, as defined in
+/// [RFC 7230, section 3.2.6](https://tools.ietf.org/html/rfc7230#section-3.2.6).
+pub fn is_token_char(c: char) -> bool {
+    (c >= '0' && c <= '9') || (c >= 'A' && c <= 'Z') || (c >= 'a' && c <= 'z') ||
+    "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// Whether the value is a non-empty `token`.
+pub fn is_token(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(is_token_char)
+}
+
+/// Whether the value is a `token68`, as defined in
+/// [RFC 7235, section 2.1](https://tools.ietf.org/html/rfc7235#section-2.1).
+pub fn is_token68(value: &str) -> bool {
+    let mut padding = false;
+    for c in value.chars() {
+        if c == '=' {
+            padding = true;
+        } else if padding ||
+                  !((c >= '0' && c <= '9') || (c >= 'A' && c <= 'Z') ||
+                    (c >= 'a' && c <= 'z') || "-._~+/".contains(c)) {
+            return false;
+        }
+    }
+    !value.is_empty() && !value.starts_with('=')
+}
+
+/// Whether the character can appear unescaped in a `quoted-string`, i.e., it is `qdtext`.
+fn is_qdtext(c: char) -> bool {
+    c == '\t' || c == ' ' || c == '!' || (c >= '#' && c <= '[') || (c >= ']' && c <= '~') ||
+    c as u32 >= 0x80
+}
+
+/// Whether the character can be escaped in a `quoted-string`, i.e., it can follow the backslash
+/// of a `quoted-pair`.
+fn is_quoted_pair_char(c: char) -> bool {
+    c == '\t' || c == ' ' || (c >= '!' && c <= '~') || c as u32 >= 0x80
+}
+
+/// Serializes a value as a `quoted-string`, escaping double quotes and backslashes.
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Append a header parameter to a serialized header. Values that are not tokens are always
+/// serialized as a `quoted-string`, even if `quoted` is `false`.
 pub fn append_parameter(serialized: &mut String, key: &str, value: &str, quoted: bool) {
     if !serialized.is_empty() {
         serialized.push_str(", ")
     }
     serialized.push_str(key);
-    serialized.push_str("=");
-    if quoted {
-        serialized.push_str("\"");
+    serialized.push('=');
+    if quoted || !is_token(value) {
+        serialized.push_str(&quote(value));
+    } else {
+        serialized.push_str(value);
     }
-    serialized.push_str(value);
-    if quoted {
-        serialized.push_str("\"");
+}
+
+/// Removes the given auth-scheme from the beginning of a challenge or set of credentials. Returns
+/// `None` if the value does not start with the auth-scheme, and an empty string if the value only
+/// consists of the auth-scheme.
+pub fn strip_scheme<'a>(s: &'a str, scheme: &str) -> Option<&'a str> {
+    let value = s.trim();
+    match value.find(char::is_whitespace) {
+        Some(pos) if UniCase(&value[..pos]) == UniCase(scheme) => Some(value[pos..].trim()),
+        None if UniCase(value) == UniCase(scheme) => Some(""),
+        _ => None,
     }
 }
 
-fn from_comma_delimited(s: &str) -> Vec<&str> {
-    let mut result = Vec::new();
+/// Whether a comma-delimited element of a header value is the beginning of a new challenge, i.e.,
+/// it starts with an auth-scheme instead of being an auth-param.
+fn starts_challenge(element: &str) -> bool {
+    match element.find(|c: char| c == '=' || c.is_whitespace()) {
+        Some(pos) => !element[pos..].trim().starts_with('='),
+        None => true,
+    }
+}
 
-    // split at comma unless it is surrounded by quot marks
-    let mut begin: usize = 0;
+/// Splits a header value that contains one or more challenges (such as `WWW-Authenticate`) into
+/// the individual challenges, each of which starts with its auth-scheme.
+///
+/// The syntax is described in
+/// [RFC 7235, section 4.1](https://tools.ietf.org/html/rfc7235#section-4.1).
+pub fn split_challenges(s: &str) -> Vec<&str> {
+    let mut challenges = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    for (begin, end) in split_list(s) {
+        let element = s[begin..end].trim();
+        if element.is_empty() {
+            continue;
+        }
+        if starts_challenge(element) {
+            if let Some((challenge_begin, challenge_end)) = current {
+                challenges.push(s[challenge_begin..challenge_end].trim());
+            }
+            current = Some((begin, end));
+        } else if let Some((challenge_begin, _)) = current {
+            current = Some((challenge_begin, end));
+        }
+    }
+    if let Some((challenge_begin, challenge_end)) = current {
+        challenges.push(s[challenge_begin..challenge_end].trim());
+    }
+
+    challenges
+}
+
+/// Splits a comma-separated list (the `#rule` of
+/// [RFC 7230, section 7](https://tools.ietf.org/html/rfc7230#section-7)) into the byte ranges of
+/// its elements, ignoring commas inside of quoted-strings. The elements are not trimmed.
+fn split_list(s: &str) -> Vec<(usize, usize)> {
+    let mut elements: Vec<(usize, usize)> = Vec::new();
+    let mut element_start: usize = 0;
     let mut open_quotation = false;
-    for (pos, c) in s.chars().enumerate() {
-        if c == ',' && !open_quotation {
-            let slice = s[begin..pos].trim();
-            if !slice.is_empty() {
-                result.push(slice);
+    let mut escaped = false;
+    for (pos, c) in s.char_indices() {
+        if open_quotation {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                open_quotation = false;
             }
-            begin = pos + 1;
         } else if c == '"' {
-            open_quotation = !open_quotation;
+            open_quotation = true;
+        } else if c == ',' {
+            elements.push((element_start, pos));
+            element_start = pos + 1;
         }
     }
+    elements.push((element_start, s.len()));
+    elements
+}
 
-    // add the chunk after the last comma
-    if begin < s.len() {
-        let slice = s[begin..].trim();
-        if !slice.is_empty() {
-            result.push(slice);
+/// Removes leading bad whitespace (`BWS`), i.e., spaces and horizontal tabs.
+fn skip_whitespace(s: &str) -> &str {
+    match s.find(|c: char| c != ' ' && c != '\t') {
+        Some(pos) => &s[pos..],
+        None => "",
+    }
+}
+
+/// Parses a `quoted-string` at the beginning of `s`, returning its unescaped value and the rest of
+/// `s` after the closing quote.
+fn parse_quoted_string(s: &str) -> Result<(String, &str), Error> {
+    let mut value = String::new();
+    let mut escaped = false;
+    for (pos, c) in s.char_indices().skip(1) {
+        if escaped {
+            if !is_quoted_pair_char(c) {
+                return Err(Error::MalformedParameters);
+            }
+            value.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            return Ok((value, &s[pos + 1..]));
+        } else if is_qdtext(c) {
+            value.push(c);
+        } else {
+            return Err(Error::MalformedParameters);
         }
     }
+    Err(Error::MalformedParameters)
+}
 
-    result
+/// Parses a single trimmed `auth-param`: a token, followed by `=` (optionally surrounded by
+/// whitespace), followed by either a token or a quoted-string.
+fn parse_auth_param(element: &str) -> Result<AuthParam, Error> {
+    let name_end = element.find(|c: char| !is_token_char(c)).unwrap_or(element.len());
+    let name = &element[..name_end];
+    let rest = skip_whitespace(&element[name_end..]);
+    if name.is_empty() || !rest.starts_with('=') {
+        return Err(Error::MalformedParameters);
+    }
+    let rest = skip_whitespace(&rest[1..]);
+    if rest.starts_with('"') {
+        let (value, remainder) = parse_quoted_string(rest)?;
+        if !remainder.trim().is_empty() {
+            return Err(Error::MalformedParameters);
+        }
+        Ok(AuthParam {
+            name: name.to_owned(),
+            value: value,
+            quoted: true,
+        })
+    } else if is_token(rest) {
+        Ok(AuthParam {
+            name: name.to_owned(),
+            value: rest.to_owned(),
+            quoted: false,
+        })
+    } else {
+        Err(Error::MalformedParameters)
+    }
 }
 
-pub fn parse_parameters(s: &str) -> HashMap<UniCase<String>, String> {
-    let parameters = from_comma_delimited(s);
-    let mut param_map: HashMap<UniCase<String>, String> = HashMap::with_capacity(parameters.len());
-    for parameter in parameters {
-        let parts: Vec<&str> = parameter.splitn(2, '=').collect();
-        if parts.len() < 2 {
+/// Parses a comma-separated list of auth-params, as described in
+/// [RFC 7235, section 2.1](https://tools.ietf.org/html/rfc7235#section-2.1), in the order that
+/// they were specified. Quoted-strings are unescaped, and empty list elements are ignored.
+///
+/// Returns an error if an element is not an auth-param, or if a parameter name occurs more than
+/// once (names are case-insensitive).
+pub fn parse_auth_params(s: &str) -> Result<Vec<AuthParam>, Error> {
+    let mut parameters: Vec<AuthParam> = Vec::new();
+    for (begin, end) in split_list(s) {
+        let element = s[begin..end].trim();
+        if element.is_empty() {
             continue;
         }
-        param_map.insert(UniCase(parts[0].trim().to_owned()),
-                         parts[1].trim().trim_matches('"').to_owned());
+        let parameter = parse_auth_param(element)?;
+        if parameters.iter()
+            .any(|existing| UniCase(&existing.name[..]) == UniCase(&parameter.name[..])) {
+            return Err(Error::DuplicateParameter(parameter.name));
+        }
+        parameters.push(parameter);
+    }
+
+    Ok(parameters)
+}
+
+/// How the value of a known parameter is decoded. Each header type declares the decoding of each
+/// of its parameters, so that values are never decoded more than once (or at all, in the case of
+/// `uri`, which has to match the request target exactly).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decoding {
+    /// A token, such as `algorithm` or `nc`, which is used as is. Some clients quote tokens, so
+    /// quoted-strings are accepted as well, as long as their content is a token.
+    Token,
+    /// A quoted-string, such as `realm` or `uri`, which is used as is after removing its quotes
+    /// and escapes. Tokens are accepted as well.
+    QuotedString,
+    /// An `ext-value`, as defined in
+    /// [RFC 5987, section 3.2](https://tools.ietf.org/html/rfc5987#section-3.2), such as
+    /// `username*`. It cannot be quoted, and is left percent-encoded so that it can be parsed
+    /// with `parse_extended_value`.
+    ExtValue,
+}
+
+/// The parameters of a header type, with the decoding of each one.
+pub type Declarations = [(&'static str, Decoding)];
+
+/// Parses a comma-separated list of auth-params into a map of the `declared` parameters, keyed by
+/// the case-insensitive parameter name, and the extension parameters. See `parse_auth_params`.
+///
+/// The values of the `declared` parameters are checked against their decoding, and are malformed
+/// if they do not match it. Extension parameters are kept as specified.
+pub fn parse_parameters(s: &str,
+                        declared: &'static Declarations)
+                        -> Result<(HashMap<UniCase<String>, String>, ExtensionParameters), Error> {
+    let mut param_map: HashMap<UniCase<String>, String> = HashMap::new();
+    let mut extensions = ExtensionParameters::new(declared);
+    for parameter in parse_auth_params(s)? {
+        let decoding = declared.iter()
+            .find(|&&(name, _)| UniCase(name) == UniCase(&parameter.name[..]));
+        match decoding {
+            Some(&(name, Decoding::Token)) if !is_token(&parameter.value) => {
+                return Err(Error::MalformedParameter(name));
+            }
+            Some(&(name, Decoding::ExtValue)) if parameter.quoted => {
+                return Err(Error::MalformedParameter(name));
+            }
+            Some(_) => {
+                param_map.insert(UniCase(parameter.name), parameter.value);
+            }
+            None => {
+                extensions.insert_param(parameter)?;
+            }
+        }
     }
 
-    param_map
+    Ok((param_map, extensions))
 }
 
-pub fn unraveled_map_value(map: &HashMap<UniCase<String>, String>, key: &str) -> Option<String> {
-    let value = match map.get(&UniCase(key.to_owned())) {
-        Some(v) => v,
-        None => return None,
-    };
-    match percent_decode(value.as_bytes()).decode_utf8() {
-        Ok(string) => Some(string.into_owned()),
-        Err(_) => None,
+/// Appends the extension parameters to a serialized header.
+pub fn append_extensions(serialized: &mut String, extensions: &ExtensionParameters) {
+    for parameter in extensions {
+        append_parameter(serialized, &parameter.name, &parameter.value, parameter.quoted);
+    }
+}
+
+/// The value of a parameter, as decoded by `parse_parameters`.
+pub fn parameter_value(map: &HashMap<UniCase<String>, String>, key: &str) -> Option<String> {
+    map.get(&UniCase(key.to_owned())).cloned()
+}
+
+/// Whether the character set is `UTF-8`, which is the only one allowed by
+/// [RFC 7616](https://tools.ietf.org/html/rfc7616#section-3.3) and
+/// [RFC 7617](https://tools.ietf.org/html/rfc7617#section-2.1).
+pub fn is_utf8(charset: &Charset) -> bool {
+    UniCase(&charset.to_string()[..]) == UniCase("UTF-8")
+}
+
+/// Parses the optional `charset` parameter, rejecting any character set other than `UTF-8`.
+pub fn parse_charset(map: &HashMap<UniCase<String>, String>) -> Result<Option<Charset>, Error> {
+    match parameter_value(map, "charset") {
+        Some(value) => {
+            if UniCase(&value[..]) == UniCase("UTF-8") {
+                Ok(Some(Charset::Ext("UTF-8".to_owned())))
+            } else {
+                Err(Error::UnsupportedCharset(value))
+            }
+        }
+        None => Ok(None),
     }
 }
@@ -0,0 +1,212 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use error::Error;
+use super::{append_parameter, is_token68, parameter_value, parse_auth_params, parse_parameters,
+            quote, split_challenges, strip_scheme, AuthParam, Declarations, Decoding};
+use unicase::UniCase;
+
+const DECLARATIONS: &'static Declarations = &[("realm", Decoding::QuotedString),
+                                               ("algorithm", Decoding::Token),
+                                               ("username*", Decoding::ExtValue)];
+
+fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|&(name, value)| (name.to_owned(), value.to_owned())).collect()
+}
+
+/// The names and values of the parsed auth-params.
+fn parse_pairs(s: &str) -> Result<Vec<(String, String)>, Error> {
+    parse_auth_params(s).map(|parameters| {
+        parameters.into_iter().map(|parameter| (parameter.name, parameter.value)).collect()
+    })
+}
+
+#[test]
+fn test_parse_auth_params_with_tokens_and_quoted_strings() {
+    assert_eq!(Ok(params(&[("realm", "a, b"), ("algorithm", "MD5"), ("qop", "auth")])),
+               parse_pairs("realm=\"a, b\", algorithm=MD5, qop=\"auth\""))
+}
+
+#[test]
+fn test_parse_auth_params_with_quoted_pairs() {
+    assert_eq!(Ok(params(&[("realm", "say \"hi\", \\o/"), ("nonce", "abc")])),
+               parse_pairs("realm=\"say \\\"hi\\\", \\\\o/\", nonce=abc"))
+}
+
+#[test]
+fn test_parse_auth_params_with_whitespace_and_empty_elements() {
+    assert_eq!(Ok(params(&[("realm", "a"), ("nonce", "b")])),
+               parse_pairs(" , realm = \"a\" ,\t, nonce\t=b ,"));
+    assert_eq!(Ok(vec![]), parse_pairs(""))
+}
+
+#[test]
+fn test_parse_auth_params_with_non_ascii() {
+    assert_eq!(Ok(params(&[("realm", "Jäsøn, Doe"), ("nonce", "b")])),
+               parse_pairs("realm=\"Jäsøn, Doe\", nonce=b"));
+    assert_eq!(Err(Error::MalformedParameters), parse_pairs("realm=Jäsøn"))
+}
+
+#[test]
+fn test_parse_auth_params_with_duplicate_parameter() {
+    assert_eq!(Err(Error::DuplicateParameter("REALM".to_owned())),
+               parse_pairs("realm=\"a\", nonce=b, REALM=\"c\""))
+}
+
+#[test]
+fn test_parse_malformed_auth_params() {
+    for data in &["realm",
+                  "=\"a\"",
+                  "realm=",
+                  "realm=a b",
+                  "realm=/dir",
+                  "realm=\"a\" b",
+                  "realm=\"unterminated",
+                  "realm=\"unterminated\\\"",
+                  "realm=\"line\nbreak\"",
+                  "re alm=\"a\""] {
+        assert_eq!(Err(Error::MalformedParameters), parse_pairs(data));
+    }
+}
+
+#[test]
+fn test_parse_auth_params_records_quoting() {
+    let expected = vec![AuthParam {
+                            name: "Realm".to_owned(),
+                            value: "a".to_owned(),
+                            quoted: true,
+                        },
+                        AuthParam {
+                            name: "nc".to_owned(),
+                            value: "00000001".to_owned(),
+                            quoted: false,
+                        }];
+    assert_eq!(Ok(expected), parse_auth_params("Realm=\"a\", nc=00000001"))
+}
+
+#[test]
+fn test_parse_parameters_is_case_insensitive() {
+    let (parameters, _) = parse_parameters("Realm=\"a\"", DECLARATIONS)
+        .expect("Could not parse parameters");
+    assert_eq!(Some(&"a".to_owned()), parameters.get(&UniCase("realm".to_owned())));
+    assert_eq!(Some("a".to_owned()), parameter_value(&parameters, "REALM"))
+}
+
+#[test]
+fn test_parse_parameters_does_not_percent_decode() {
+    let (parameters, extensions) =
+        parse_parameters("realm=\"100%25\", username*=UTF-8''J%C3%A4s%C3%B8n, uri=\"/a%20b\"",
+                         DECLARATIONS)
+            .expect("Could not parse parameters");
+    assert_eq!(Some("100%25".to_owned()), parameter_value(&parameters, "realm"));
+    assert_eq!(Some("UTF-8''J%C3%A4s%C3%B8n".to_owned()),
+               parameter_value(&parameters, "username*"));
+    assert_eq!(None, parameter_value(&parameters, "uri"));
+    assert_eq!(Some("/a%20b"), extensions.get("uri"))
+}
+
+#[test]
+fn test_parse_parameters_with_quoted_ext_value() {
+    assert_eq!(Err(Error::MalformedParameter("username*")),
+               parse_parameters("username*=\"UTF-8''a\"", DECLARATIONS))
+}
+
+#[test]
+fn test_parse_parameters_with_quoted_token() {
+    let (parameters, _) = parse_parameters("algorithm=\"MD5\"", DECLARATIONS)
+        .expect("Could not parse parameters");
+    assert_eq!(Some("MD5".to_owned()), parameter_value(&parameters, "algorithm"))
+}
+
+#[test]
+fn test_parse_parameters_with_quoted_non_token() {
+    assert_eq!(Err(Error::MalformedParameter("algorithm")),
+               parse_parameters("algorithm=\"MD5, SHA-256\"", DECLARATIONS));
+    assert_eq!(Err(Error::MalformedParameter("algorithm")),
+               parse_parameters("algorithm=\"\"", DECLARATIONS))
+}
+
+#[test]
+fn test_parse_parameters_with_unquoted_quoted_string() {
+    let (parameters, _) = parse_parameters("realm=example", DECLARATIONS)
+        .expect("Could not parse parameters");
+    assert_eq!(Some("example".to_owned()), parameter_value(&parameters, "realm"))
+}
+
+#[test]
+fn test_quote() {
+    assert_eq!("\"\"", quote(""));
+    assert_eq!("\"a b\"", quote("a b"));
+    assert_eq!("\"say \\\"hi\\\", \\\\o/\"", quote("say \"hi\", \\o/"))
+}
+
+#[test]
+fn test_append_parameter() {
+    let mut serialized = String::new();
+    append_parameter(&mut serialized, "realm", "say \"hi\"", true);
+    append_parameter(&mut serialized, "algorithm", "MD5", false);
+    append_parameter(&mut serialized, "opaque", "a b", false);
+    append_parameter(&mut serialized, "stale", "", false);
+    assert_eq!("realm=\"say \\\"hi\\\"\", algorithm=MD5, opaque=\"a b\", stale=\"\"",
+               serialized);
+    assert_eq!(Ok(params(&[("realm", "say \"hi\""),
+                           ("algorithm", "MD5"),
+                           ("opaque", "a b"),
+                           ("stale", "")])),
+               parse_pairs(&serialized))
+}
+
+#[test]
+fn test_is_token68() {
+    assert!(is_token68("QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+    assert!(is_token68("a-._~+/b"));
+    assert!(!is_token68(""));
+    assert!(!is_token68("=="));
+    assert!(!is_token68("ab=c"));
+    assert!(!is_token68("a b"));
+    assert!(!is_token68("Jäsøn"))
+}
+
+#[test]
+fn test_split_challenges_with_escaped_quotes() {
+    assert_eq!(vec!["Basic realm=\"a \\\", Digest b\"", "Digest realm=\"c\", nonce=d"],
+               split_challenges("Basic realm=\"a \\\", Digest b\", Digest realm=\"c\", nonce=d"))
+}
+
+#[test]
+fn test_split_challenges_with_non_ascii() {
+    assert_eq!(vec!["Basic realm=\"Jäsøn, Doe\"", "Newauth realm=\"ø\""],
+               split_challenges("Basic realm=\"Jäsøn, Doe\", Newauth realm=\"ø\""))
+}
+
+#[test]
+fn test_strip_scheme_with_non_ascii_whitespace() {
+    assert_eq!(Some("realm=\"a\""), strip_scheme("Basic\u{3000}realm=\"a\"", "Basic"));
+    assert_eq!(None, strip_scheme("Digest realm=\"a\"", "Basic"))
+}
+
+#[test]
+fn test_strip_scheme_without_parameters() {
+    assert_eq!(Some(""), strip_scheme(" basic ", "Basic"));
+    assert_eq!(None, strip_scheme("Basically", "Basic"))
+}
@@ -0,0 +1,213 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! Preparation of usernames and passwords via the PRECIS profiles of
+//! [RFC 7613](https://tools.ietf.org/html/rfc7613), which
+//! [RFC 7616, section 4](https://tools.ietf.org/html/rfc7616#section-4) recommends when the
+//! `charset` is `UTF-8`.
+//!
+//! Prepared credentials are compared and hashed consistently, even if the user typed them
+//! differently, for example, with a precomposed `ä` instead of `a` followed by a combining
+//! diaeresis, or with fullwidth instead of ASCII letters.
+
+use error::Error;
+use unicode_bidi::{BidiClass, bidi_class};
+use unicode_categories::UnicodeCategories;
+use unicode_normalization::UnicodeNormalization;
+
+mod test;
+
+/// The PRECIS profiles for usernames and passwords.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// For usernames that are compared case-insensitively, as defined in
+    /// [RFC 7613, section 3.2](https://tools.ietf.org/html/rfc7613#section-3.2).
+    UsernameCaseMapped,
+    /// For usernames that are compared case-sensitively, as defined in
+    /// [RFC 7613, section 3.3](https://tools.ietf.org/html/rfc7613#section-3.3).
+    UsernameCasePreserved,
+    /// For passwords, as defined in
+    /// [RFC 7613, section 4.2](https://tools.ietf.org/html/rfc7613#section-4.2).
+    OpaqueString,
+}
+
+impl Profile {
+    /// Prepares the string according to the profile, and enforces the profile's rules. The result
+    /// is the string that should be used when comparing or hashing credentials.
+    pub fn enforce(&self, s: &str) -> Result<String, Error> {
+        match *self {
+            Profile::UsernameCaseMapped => enforce_username(&map_width(s).to_lowercase()),
+            Profile::UsernameCasePreserved => enforce_username(&map_width(s)),
+            Profile::OpaqueString => enforce_opaque_string(s),
+        }
+    }
+}
+
+/// Maps fullwidth and halfwidth characters to their decomposition mappings, e.g., `Ａ` to `A`.
+fn map_width(s: &str) -> String {
+    let mut mapped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c >= '\u{ff00}' && c <= '\u{ffef}' {
+            mapped.extend(c.to_string().nfkc());
+        } else {
+            mapped.push(c);
+        }
+    }
+    mapped
+}
+
+/// The ranges of code points with the `Default_Ignorable_Code_Point` property, which
+/// [RFC 7564, section 9.13](https://tools.ietf.org/html/rfc7564#section-9.13) disallows, e.g., the
+/// zero width space.
+const DEFAULT_IGNORABLE_CODE_POINTS: &'static [(u32, u32)] = &[(0xad, 0xad),
+                                                               (0x34f, 0x34f),
+                                                               (0x61c, 0x61c),
+                                                               (0x115f, 0x1160),
+                                                               (0x17b4, 0x17b5),
+                                                               (0x180b, 0x180f),
+                                                               (0x200b, 0x200f),
+                                                               (0x202a, 0x202e),
+                                                               (0x2060, 0x206f),
+                                                               (0x3164, 0x3164),
+                                                               (0xfe00, 0xfe0f),
+                                                               (0xfeff, 0xfeff),
+                                                               (0xffa0, 0xffa0),
+                                                               (0xfff0, 0xfff8),
+                                                               (0x1bca0, 0x1bca3),
+                                                               (0x1d173, 0x1d17a),
+                                                               (0xe0000, 0xe0fff)];
+
+/// Whether a character is a default ignorable code point or a noncharacter, which neither string
+/// class allows.
+fn is_ignorable(c: char) -> bool {
+    let code_point = c as u32;
+    DEFAULT_IGNORABLE_CODE_POINTS.iter()
+        .any(|&(first, last)| code_point >= first && code_point <= last) ||
+    (code_point >= 0xfdd0 && code_point <= 0xfdef) || code_point & 0xfffe == 0xfffe
+}
+
+/// Whether a character is in the `LetterDigits` categories of
+/// [RFC 5892, section 2.1](https://tools.ietf.org/html/rfc5892#section-2.1), i.e., `Ll`, `Lu`,
+/// `Lo`, `Nd`, `Lm`, `Mn` or `Mc`. Other numbers, such as `½` or `Ⅻ`, are not included.
+fn is_letter_digit(c: char) -> bool {
+    c.is_letter_lowercase() || c.is_letter_uppercase() || c.is_letter_other() ||
+    c.is_number_decimal_digit() || c.is_letter_modifier() || c.is_mark_nonspacing() ||
+    c.is_mark_spacing_combining()
+}
+
+/// Whether a character belongs to the `IdentifierClass` of
+/// [RFC 7564, section 4.2](https://tools.ietf.org/html/rfc7564#section-4.2), i.e., it is either a
+/// printable ASCII character, or in the `LetterDigits` categories.
+fn is_identifier_char(c: char) -> bool {
+    if c <= '\u{7f}' {
+        c >= '!' && c <= '~'
+    } else {
+        is_letter_digit(c) && !is_ignorable(c)
+    }
+}
+
+/// Whether a character belongs to the `FreeformClass` of
+/// [RFC 7564, section 4.3](https://tools.ietf.org/html/rfc7564#section-4.3), which excludes
+/// control characters, line and paragraph separators, and ignorable code points.
+fn is_freeform_char(c: char) -> bool {
+    !c.is_control() && c != '\u{2028}' && c != '\u{2029}' && !is_ignorable(c)
+}
+
+/// Whether a character is a space other than U+0020, i.e., in the `Zs` general category.
+fn is_non_ascii_space(c: char) -> bool {
+    match c {
+        '\u{a0}' | '\u{1680}' | '\u{202f}' | '\u{205f}' | '\u{3000}' => true,
+        _ => c >= '\u{2000}' && c <= '\u{200a}',
+    }
+}
+
+/// Whether a string satisfies the Bidi Rule of
+/// [RFC 5893, section 2](https://tools.ietf.org/html/rfc5893#section-2). Strings without
+/// right-to-left characters always do.
+fn satisfies_bidi_rule(s: &str) -> bool {
+    use unicode_bidi::BidiClass::{AL, AN, BN, CS, EN, ES, ET, L, NSM, ON, R};
+
+    let classes: Vec<BidiClass> = s.chars().map(bidi_class).collect();
+    let is_rtl = |class: &BidiClass| *class == R || *class == AL || *class == AN;
+    if !classes.iter().any(&is_rtl) {
+        return true;
+    }
+    let last = classes.iter().rev().find(|class| **class != NSM);
+    match classes.first() {
+        Some(&R) | Some(&AL) => {
+            classes.iter().all(|class| match *class {
+                R | AL | AN | EN | ES | CS | ET | ON | BN | NSM => true,
+                _ => false,
+            }) &&
+            match last {
+                Some(&R) | Some(&AL) | Some(&EN) | Some(&AN) => true,
+                _ => false,
+            } && !(classes.contains(&EN) && classes.contains(&AN))
+        }
+        Some(&L) => {
+            classes.iter().all(|class| match *class {
+                L | EN | ES | CS | ET | ON | BN | NSM => true,
+                _ => false,
+            }) &&
+            match last {
+                Some(&L) | Some(&EN) => true,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Normalizes a (width- and case-mapped) username, and enforces the rules that are shared by both
+/// username profiles. A username consists of one or more userparts, separated by single spaces.
+fn enforce_username(s: &str) -> Result<String, Error> {
+    let username: String = s.nfc().collect();
+    if username.is_empty() {
+        return Err(Error::EmptyCredential);
+    }
+    for userpart in username.split(' ') {
+        if userpart.is_empty() {
+            return Err(Error::DisallowedCharacter(' '));
+        }
+        if let Some(c) = userpart.chars().find(|c| !is_identifier_char(*c)) {
+            return Err(Error::DisallowedCharacter(c));
+        }
+        if !satisfies_bidi_rule(userpart) {
+            return Err(Error::BidiRuleViolation);
+        }
+    }
+
+    Ok(username)
+}
+
+fn enforce_opaque_string(s: &str) -> Result<String, Error> {
+    let mapped: String = s.chars()
+        .map(|c| if is_non_ascii_space(c) { ' ' } else { c })
+        .collect();
+    let password: String = mapped.nfc().collect();
+    if password.is_empty() {
+        return Err(Error::EmptyCredential);
+    }
+    match password.chars().find(|c| !is_freeform_char(*c)) {
+        Some(c) => Err(Error::DisallowedCharacter(c)),
+        None => Ok(password),
+    }
+}
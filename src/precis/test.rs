@@ -0,0 +1,94 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use error::Error;
+use super::Profile;
+
+#[test]
+fn test_username_case_preserved() {
+    let profile = Profile::UsernameCasePreserved;
+    assert_eq!(Ok("juliet@example.com".to_owned()),
+               profile.enforce("juliet@example.com"));
+    assert_eq!(Ok("Juliet".to_owned()), profile.enforce("Juliet"));
+    assert_eq!(Ok("J\u{e4}s\u{f8}n Doe".to_owned()),
+               profile.enforce("Ja\u{308}s\u{f8}n Doe"));
+}
+
+#[test]
+fn test_username_case_mapped() {
+    let profile = Profile::UsernameCaseMapped;
+    assert_eq!(Ok("juliet".to_owned()), profile.enforce("Juliet"));
+    assert_eq!(Ok("j\u{e4}s\u{f8}n doe".to_owned()),
+               profile.enforce("JA\u{308}S\u{d8}N Doe"));
+}
+
+#[test]
+fn test_username_width_mapping() {
+    let fullwidth = "\u{ff2a}\u{ff55}\u{ff4c}\u{ff49}\u{ff45}\u{ff54}";
+    assert_eq!(Ok("Juliet".to_owned()),
+               Profile::UsernameCasePreserved.enforce(fullwidth));
+}
+
+#[test]
+fn test_username_errors() {
+    let profile = Profile::UsernameCasePreserved;
+    assert_eq!(Err(Error::EmptyCredential), profile.enforce(""));
+    assert_eq!(Err(Error::DisallowedCharacter(' ')), profile.enforce("foo  bar"));
+    assert_eq!(Err(Error::DisallowedCharacter(' ')), profile.enforce(" foo"));
+    assert_eq!(Err(Error::DisallowedCharacter('\t')), profile.enforce("foo\tbar"));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{2665}')),
+               profile.enforce("foo\u{2665}"));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{a0}')), profile.enforce("foo\u{a0}bar"));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{bd}')), profile.enforce("a\u{bd}"));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{216b}')), profile.enforce("\u{216b}"));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{200b}')), profile.enforce("foo\u{200b}"));
+}
+
+#[test]
+fn test_username_bidi_rule() {
+    let profile = Profile::UsernameCasePreserved;
+    assert_eq!(Ok("\u{5e9}\u{5dc}\u{5d5}\u{5dd}".to_owned()),
+               profile.enforce("\u{5e9}\u{5dc}\u{5d5}\u{5dd}"));
+    assert_eq!(Ok("\u{5e9}1".to_owned()), profile.enforce("\u{5e9}1"));
+    assert_eq!(Err(Error::BidiRuleViolation), profile.enforce("a\u{5e9}"));
+    assert_eq!(Err(Error::BidiRuleViolation), profile.enforce("1\u{5e9}"));
+}
+
+#[test]
+fn test_opaque_string() {
+    let profile = Profile::OpaqueString;
+    assert_eq!(Ok("correct horse battery staple".to_owned()),
+               profile.enforce("correct horse battery staple"));
+    assert_eq!(Ok("Correct Horse".to_owned()), profile.enforce("Correct\u{3000}Horse"));
+    assert_eq!(Ok("\u{3c0}\u{df}\u{e5}".to_owned()), profile.enforce("\u{3c0}\u{df}a\u{30a}"));
+    assert_eq!(Ok("\u{ff2a}".to_owned()), profile.enforce("\u{ff2a}"));
+}
+
+#[test]
+fn test_opaque_string_errors() {
+    let profile = Profile::OpaqueString;
+    assert_eq!(Err(Error::EmptyCredential), profile.enforce(""));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{7}')), profile.enforce("foo\u{7}"));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{200b}')), profile.enforce("foo\u{200b}bar"));
+    assert_eq!(Err(Error::DisallowedCharacter('\u{feff}')), profile.enforce("\u{feff}foo"));
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Implementations of the `Proxy-Authenticate`, `Proxy-Authorization`, and
+//! `Proxy-Authentication-Info` headers.
+//!
+//! These headers are described in
+//! [RFC 7235, section 4.3](https://tools.ietf.org/html/rfc7235#section-4.3),
+//! [RFC 7235, section 4.4](https://tools.ietf.org/html/rfc7235#section-4.4), and
+//! [RFC 7615, section 4](https://tools.ietf.org/html/rfc7615#section-4), respectively. Their
+//! values are identical to the `WWW-Authenticate`, `Authorization`, and `Authentication-Info`
+//! headers.
+
+use authentication_info::AuthenticationInfo;
+use hyper::{Error as HyperError, Result as HyperResult};
+use hyper::header::{Header, HeaderFormat, Scheme};
+use hyper::header::parsing::from_one_raw_str;
+use parsing::strip_scheme;
+use std::any::Any;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use www_authenticate::WwwAuthenticate;
+
+mod test;
+
+/// The `Proxy-Authenticate` header, which contains a `Digest` challenge from a proxy.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProxyAuthenticate(pub WwwAuthenticate);
+
+impl Deref for ProxyAuthenticate {
+    type Target = WwwAuthenticate;
+
+    fn deref(&self) -> &WwwAuthenticate {
+        &self.0
+    }
+}
+
+impl DerefMut for ProxyAuthenticate {
+    fn deref_mut(&mut self) -> &mut WwwAuthenticate {
+        &mut self.0
+    }
+}
+
+impl Header for ProxyAuthenticate {
+    fn header_name() -> &'static str {
+        "Proxy-Authenticate"
+    }
+
+    fn parse_header(raw: &[Vec<u8>]) -> HyperResult<ProxyAuthenticate> {
+        WwwAuthenticate::parse_header(raw).map(ProxyAuthenticate)
+    }
+}
+
+impl HeaderFormat for ProxyAuthenticate {
+    fn fmt_header(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_header(f)
+    }
+}
+
+/// The `Proxy-Authorization` header, which contains the credentials for a proxy. Like Hyper's
+/// `Authorization` header, it is parameterized by the authentication scheme.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProxyAuthorization<S: Scheme>(pub S);
+
+impl<S: Scheme> Deref for ProxyAuthorization<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S: Scheme> DerefMut for ProxyAuthorization<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.0
+    }
+}
+
+impl<S: Scheme + Any> Header for ProxyAuthorization<S>
+    where <S as FromStr>::Err: 'static
+{
+    fn header_name() -> &'static str {
+        "Proxy-Authorization"
+    }
+
+    fn parse_header(raw: &[Vec<u8>]) -> HyperResult<ProxyAuthorization<S>> {
+        let value: String = from_one_raw_str(raw)?;
+        let credentials = match <S as Scheme>::scheme() {
+            Some(scheme) => {
+                match strip_scheme(&value, scheme) {
+                    Some(credentials) => credentials,
+                    None => return Err(HyperError::Header),
+                }
+            }
+            None => &value[..],
+        };
+        match S::from_str(credentials) {
+            Ok(parsed) => Ok(ProxyAuthorization(parsed)),
+            Err(_) => Err(HyperError::Header),
+        }
+    }
+}
+
+impl<S: Scheme + Any> HeaderFormat for ProxyAuthorization<S>
+    where <S as FromStr>::Err: 'static
+{
+    fn fmt_header(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(scheme) = <S as Scheme>::scheme() {
+            write!(f, "{} ", scheme)?;
+        }
+        self.0.fmt_scheme(f)
+    }
+}
+
+/// The `Proxy-Authentication-Info` header, which a proxy sends after a successful authentication.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProxyAuthenticationInfo(pub AuthenticationInfo);
+
+impl Deref for ProxyAuthenticationInfo {
+    type Target = AuthenticationInfo;
+
+    fn deref(&self) -> &AuthenticationInfo {
+        &self.0
+    }
+}
+
+impl DerefMut for ProxyAuthenticationInfo {
+    fn deref_mut(&mut self) -> &mut AuthenticationInfo {
+        &mut self.0
+    }
+}
+
+impl Header for ProxyAuthenticationInfo {
+    fn header_name() -> &'static str {
+        "Proxy-Authentication-Info"
+    }
+
+    fn parse_header(raw: &[Vec<u8>]) -> HyperResult<ProxyAuthenticationInfo> {
+        AuthenticationInfo::parse_header(raw).map(ProxyAuthenticationInfo)
+    }
+}
+
+impl HeaderFormat for ProxyAuthenticationInfo {
+    fn fmt_header(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_header(f)
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Protection against replayed `Authorization` headers, via the nonce count.
+//!
+//! Per [RFC 7616, section 3.4](https://tools.ietf.org/html/rfc7616#section-3.4), the client
+//! increments the nonce count (`nc`) for every request that it sends with the same nonce. A server
+//! that remembers the highest nonce count it has seen can detect a request that has been replayed.
+
+use digest::Digest;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use super::types::NonceCount;
+
+mod test;
+
+/// Keeps track of the highest nonce count seen for each nonce and client nonce pair.
+///
+/// Nonce counts must only be recorded for requests whose `response` has been validated. Otherwise,
+/// anyone who has seen a nonce and client nonce can send a very high nonce count with a bogus
+/// `response`, after which every request from the real client is rejected as a replay.
+/// `Digest::validate_response_and_nonce_count` validates in the right order.
+///
+/// Implement this trait to share the state between multiple processes, for example via a database.
+pub trait ReplayGuard {
+    /// Records `nonce_count` for the given nonce and client nonce, if it is greater than every
+    /// nonce count previously recorded for that pair. Returns `false` if the nonce count was
+    /// repeated or went backwards, i.e., the request is a replay.
+    ///
+    /// Only call this after the `response` of the request has been validated.
+    fn accept(&self, nonce: &str, client_nonce: &str, nonce_count: &NonceCount) -> bool;
+
+    /// Forgets every nonce count recorded for `nonce`.
+    fn evict(&self, nonce: &str);
+
+    /// Forgets every nonce count recorded for nonces that have expired.
+    fn evict_expired(&self);
+
+    /// Records the nonce count from a `Digest` header. Returns `false` if the request is a replay,
+    /// or if the header does not have both a nonce count and a client nonce (i.e., it was sent in
+    /// RFC 2069 mode).
+    ///
+    /// Only call this after the `response` of the request has been validated.
+    fn accept_digest(&self, digest: &Digest) -> bool {
+        match (&digest.client_nonce, &digest.nonce_count) {
+            (&Some(ref client_nonce), &Some(ref nonce_count)) => {
+                self.accept(&digest.nonce, client_nonce, nonce_count)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TrackedNonce {
+    first_seen: Instant,
+    nonce_counts: HashMap<String, u32>,
+}
+
+/// A `ReplayGuard` that keeps its state in memory, for use by a single process.
+#[derive(Debug)]
+pub struct InMemoryReplayGuard {
+    lifetime: Duration,
+    nonces: Mutex<HashMap<String, TrackedNonce>>,
+}
+
+impl InMemoryReplayGuard {
+    /// Creates a replay guard, given how long a nonce is tracked after it is first seen. This
+    /// should be at least as long as the lifetime of the nonces issued by the server.
+    pub fn new(lifetime: Duration) -> InMemoryReplayGuard {
+        InMemoryReplayGuard {
+            lifetime: lifetime,
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The number of nonces that are being tracked.
+    pub fn len(&self) -> usize {
+        self.nonces.lock().expect("Replay guard is poisoned").len()
+    }
+
+    /// Whether no nonces are being tracked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn evict_expired_at(&self, now: Instant) {
+        let mut nonces = self.nonces.lock().expect("Replay guard is poisoned");
+        let expired: Vec<String> = nonces.iter()
+            .filter(|&(_, tracked)| now > tracked.first_seen + self.lifetime)
+            .map(|(nonce, _)| nonce.clone())
+            .collect();
+        for nonce in expired {
+            nonces.remove(&nonce);
+        }
+    }
+}
+
+impl ReplayGuard for InMemoryReplayGuard {
+    fn accept(&self, nonce: &str, client_nonce: &str, nonce_count: &NonceCount) -> bool {
+        let NonceCount(count) = *nonce_count;
+        let mut nonces = self.nonces.lock().expect("Replay guard is poisoned");
+        let tracked = nonces.entry(nonce.to_owned()).or_insert_with(|| {
+            TrackedNonce {
+                first_seen: Instant::now(),
+                nonce_counts: HashMap::new(),
+            }
+        });
+        let highest = tracked.nonce_counts.entry(client_nonce.to_owned()).or_insert(0);
+        if count > *highest {
+            *highest = count;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn evict(&self, nonce: &str) {
+        self.nonces.lock().expect("Replay guard is poisoned").remove(nonce);
+    }
+
+    fn evict_expired(&self) {
+        self.evict_expired_at(Instant::now())
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use digest::test_helper::rfc2617_digest_header;
+use std::time::{Duration, Instant};
+use super::{InMemoryReplayGuard, ReplayGuard};
+use super::super::types::{HashAlgorithm, NonceCount};
+
+const NONCE: &'static str = "dcd98b7102dd2f0e8b11d0f600bfb0c093";
+
+fn guard() -> InMemoryReplayGuard {
+    InMemoryReplayGuard::new(Duration::from_secs(300))
+}
+
+#[test]
+fn test_accept_increasing_nonce_counts() {
+    let guard = guard();
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(1)));
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(2)));
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(5)))
+}
+
+#[test]
+fn test_reject_repeated_nonce_count() {
+    let guard = guard();
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(1)));
+    assert!(!guard.accept(NONCE, "0a4f113b", &NonceCount(1)))
+}
+
+#[test]
+fn test_reject_decreasing_nonce_count() {
+    let guard = guard();
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(3)));
+    assert!(!guard.accept(NONCE, "0a4f113b", &NonceCount(2)))
+}
+
+#[test]
+fn test_reject_zero_nonce_count() {
+    assert!(!guard().accept(NONCE, "0a4f113b", &NonceCount(0)))
+}
+
+#[test]
+fn test_nonce_counts_are_tracked_per_client_nonce() {
+    let guard = guard();
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(1)));
+    assert!(guard.accept(NONCE, "b24ce2519b8cdb10", &NonceCount(1)));
+    assert!(guard.accept("other nonce", "0a4f113b", &NonceCount(1)));
+    assert_eq!(2, guard.len())
+}
+
+#[test]
+fn test_evict() {
+    let guard = guard();
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(1)));
+    guard.evict(NONCE);
+    assert!(guard.is_empty());
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(1)))
+}
+
+#[test]
+fn test_evict_expired() {
+    let guard = guard();
+    assert!(guard.accept(NONCE, "0a4f113b", &NonceCount(1)));
+    guard.evict_expired();
+    assert_eq!(1, guard.len());
+    guard.evict_expired_at(Instant::now() + Duration::from_secs(301));
+    assert!(guard.is_empty())
+}
+
+#[test]
+fn test_accept_digest() {
+    let guard = guard();
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert!(guard.accept_digest(&digest));
+    assert!(!guard.accept_digest(&digest))
+}
+
+#[test]
+fn test_accept_digest_sans_nonce_count() {
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.nonce_count = None;
+    assert!(!guard().accept_digest(&digest))
+}
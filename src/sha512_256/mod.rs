@@ -0,0 +1,191 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! An implementation of the SHA-512/256 hash function, as specified in
+//! [FIPS 180-4, section 6.7](http://dx.doi.org/10.6028/NIST.FIPS.180-4).
+//!
+//! SHA-512/256 uses the SHA-512 compression function, but with different initial hash values, and
+//! the output is truncated to 256 bits. It is not the same as truncating the output of SHA-512.
+
+use std::cmp;
+use std::io::{self, Write};
+
+mod test;
+
+const BLOCK_SIZE: usize = 128;
+const OUTPUT_SIZE: usize = 32;
+
+/// Cf. FIPS 180-4, section 5.3.6.2
+const INITIAL_HASH: [u64; 8] = [0x22312194fc2bf72c, 0x9f555fa3c84c64c2, 0x2393b86b6f53b151,
+                                0x963877195940eabd, 0x96283ee2a88effe3, 0xbe5e1e2553863992,
+                                0x2b0199fc2c85b8aa, 0x0eb72ddc81c52ca2];
+
+/// Cf. FIPS 180-4, section 4.2.3
+const ROUND_CONSTANTS: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc, 0x3956c25bf348b538, 0x59f111f1b605d019,
+    0x923f82a4af194f9b, 0xab1c5ed5da6d8118, 0xd807aa98a3030242,
+    0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235,
+    0xc19bf174cf692694, 0xe49b69c19ef14ad2, 0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65, 0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f,
+    0xbf597fc7beef0ee4, 0xc6e00bf33da88fc2, 0xd5a79147930aa725,
+    0x06ca6351e003826f, 0x142929670a0e6e70, 0x27b70a8546d22ffc,
+    0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6,
+    0x92722c851482353b, 0xa2bfe8a14cf10364, 0xa81a664bbc423001,
+    0xc24b8b70d0f89791, 0xc76c51a30654be30, 0xd192e819d6ef5218,
+    0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8, 0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3, 0x748f82ee5defb2fc,
+    0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915,
+    0xc67178f2e372532b, 0xca273eceea26619c, 0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178, 0x06f067aa72176fba,
+    0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c, 0x4cc5d4becb3e42b6, 0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// Incrementally computes a SHA-512/256 digest.
+pub struct Sha512256 {
+    state: [u64; 8],
+    block: [u8; BLOCK_SIZE],
+    block_length: usize,
+    message_length: u64,
+}
+
+impl Sha512256 {
+    /// Creates a hasher with the initial hash values of SHA-512/256.
+    pub fn new() -> Sha512256 {
+        Sha512256 {
+            state: INITIAL_HASH,
+            block: [0; BLOCK_SIZE],
+            block_length: 0,
+            message_length: 0,
+        }
+    }
+
+    /// Hashes the next chunk of the message.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.message_length = self.message_length.wrapping_add(data.len() as u64);
+        while !data.is_empty() {
+            let length = cmp::min(BLOCK_SIZE - self.block_length, data.len());
+            self.block[self.block_length..self.block_length + length]
+                .copy_from_slice(&data[..length]);
+            self.block_length += length;
+            data = &data[length..];
+            if self.block_length == BLOCK_SIZE {
+                let block = self.block;
+                self.compress(&block);
+                self.block_length = 0;
+            }
+        }
+    }
+
+    /// Pads the message, and returns its digest.
+    pub fn finish(mut self) -> Vec<u8> {
+        let bit_length_high = self.message_length >> 61;
+        let bit_length_low = self.message_length << 3;
+        self.block[self.block_length] = 0x80;
+        for byte in &mut self.block[self.block_length + 1..] {
+            *byte = 0;
+        }
+        if self.block_length + 1 > BLOCK_SIZE - 16 {
+            let block = self.block;
+            self.compress(&block);
+            self.block = [0; BLOCK_SIZE];
+        }
+        write_u64(&mut self.block[BLOCK_SIZE - 16..BLOCK_SIZE - 8], bit_length_high);
+        write_u64(&mut self.block[BLOCK_SIZE - 8..], bit_length_low);
+        let block = self.block;
+        self.compress(&block);
+
+        let mut digest = vec![0; OUTPUT_SIZE];
+        for (chunk, word) in digest.chunks_mut(8).zip(self.state.iter()) {
+            write_u64(chunk, *word);
+        }
+        digest
+    }
+
+    // FIPS 180-4, section 6.4.2
+    fn compress(&mut self, block: &[u8; BLOCK_SIZE]) {
+        let mut schedule = [0u64; 80];
+        for (word, chunk) in schedule.iter_mut().zip(block.chunks(8)) {
+            *word = read_u64(chunk);
+        }
+        for t in 16..80 {
+            let s0 = schedule[t - 15].rotate_right(1) ^ schedule[t - 15].rotate_right(8) ^
+                     (schedule[t - 15] >> 7);
+            let s1 = schedule[t - 2].rotate_right(19) ^ schedule[t - 2].rotate_right(61) ^
+                     (schedule[t - 2] >> 6);
+            schedule[t] = schedule[t - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[t - 7])
+                .wrapping_add(s1);
+        }
+
+        let mut working = self.state;
+        for t in 0..80 {
+            let (a, b, c, d, e, f, g, h) = (working[0], working[1], working[2], working[3],
+                                            working[4], working[5], working[6], working[7]);
+            let sum1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h.wrapping_add(sum1)
+                .wrapping_add(choice)
+                .wrapping_add(ROUND_CONSTANTS[t])
+                .wrapping_add(schedule[t]);
+            let sum0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = sum0.wrapping_add(majority);
+            working = [temp1.wrapping_add(temp2), a, b, c, d.wrapping_add(temp1), e, f, g];
+        }
+
+        for (word, value) in self.state.iter_mut().zip(working.iter()) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+}
+
+impl Write for Sha512256 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |word, byte| (word << 8) | *byte as u64)
+}
+
+fn write_u64(bytes: &mut [u8], word: u64) {
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (word >> (56 - 8 * i)) as u8;
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use hex::ToHex;
+use super::Sha512256;
+
+fn digest(data: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha512256::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+// Test vectors from NIST's example values for SHA-512/256:
+// https://csrc.nist.gov/projects/cryptographic-standards-and-guidelines/example-values
+
+#[test]
+fn test_empty_message() {
+    assert_eq!("c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a",
+               digest(b"").to_hex())
+}
+
+#[test]
+fn test_one_block_message() {
+    assert_eq!("53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+               digest(b"abc").to_hex())
+}
+
+#[test]
+fn test_two_block_message() {
+    let message = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopq\
+                    klmnopqrlmnopqrsmnopqrstnopqrstu";
+    assert_eq!("3928e184fb8690f840da3988121d31be65cb9d3ef83ee6146feac861e19b563a",
+               digest(message).to_hex())
+}
+
+#[test]
+fn test_incremental_update() {
+    let message = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopq\
+                    klmnopqrlmnopqrsmnopqrstnopqrstu";
+    let mut hasher = Sha512256::new();
+    for chunk in message.chunks(7) {
+        hasher.update(chunk);
+    }
+    assert_eq!(digest(message), hasher.finish())
+}
+
+#[test]
+fn test_padding_boundaries() {
+    // 111 bytes is the longest message whose padding fits into the last block.
+    assert_eq!("0239e429f98d0ed61ee8e2a7c30afe98c1c3a80ce5dff62a107e9c538f7632ce",
+               digest(&[b'a'; 111]).to_hex());
+    assert_eq!("9216b5303edb66504570bee90e48ea5beaa5e9fe9f760bbd3e0460559fc005f6",
+               digest(&[b'a'; 112]).to_hex());
+    assert_eq!("b88f97e274f9c1d49f181c8cbd01a9c74930ad055a46ac4499a1d601f1c80bf2",
+               digest(&[b'a'; 128]).to_hex())
+}
@@ -20,16 +20,24 @@
 
 //! Common authentication types.
 
+use algorithm::{self, CustomAlgorithm};
 use crypto_hash;
-use hex::FromHex;
-use hyper::error::Error;
+use hex::{FromHex, ToHex};
+use error::Error;
 use std::collections::HashMap;
 use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::slice;
 use std::str::FromStr;
-use super::parsing::unraveled_map_value;
+use super::parsing::{is_token, parameter_value, Declarations};
+use super::sha512_256::Sha512256;
 use unicase::UniCase;
 
-/// Allowable hash algorithms for the `algorithm` parameter.
+/// Hash algorithms for the `algorithm` parameter.
+///
+/// Algorithms that are not built in are parsed as `Unknown`, so that they can be reported instead
+/// of rejecting the whole header. See the `algorithm` module for adding support for them.
 #[derive(Clone, Debug, PartialEq)]
 pub enum HashAlgorithm {
     /// `MD5`
@@ -44,6 +52,11 @@ pub enum HashAlgorithm {
     SHA512256,
     /// `SHA-512-256-sess`
     SHA512256Session,
+    /// An algorithm that was registered with an `AlgorithmRegistry`.
+    Custom(CustomAlgorithm),
+    /// An algorithm that is neither built in nor registered. It cannot be used to generate or
+    /// validate digests.
+    Unknown(String),
 }
 
 impl FromStr for HashAlgorithm {
@@ -57,7 +70,8 @@ impl FromStr for HashAlgorithm {
             "SHA-256-sess" => Ok(HashAlgorithm::SHA256Session),
             "SHA-512-256" => Ok(HashAlgorithm::SHA512256),
             "SHA-512-256-sess" => Ok(HashAlgorithm::SHA512256Session),
-            _ => Err(Error::Header),
+            "" => Err(Error::MalformedParameter("algorithm")),
+            _ => Ok(HashAlgorithm::Unknown(s.to_owned())),
         }
     }
 }
@@ -71,31 +85,136 @@ impl fmt::Display for HashAlgorithm {
             HashAlgorithm::SHA256Session => write!(f, "{}", "SHA-256-sess"),
             HashAlgorithm::SHA512256 => write!(f, "{}", "SHA-512-256"),
             HashAlgorithm::SHA512256Session => write!(f, "{}", "SHA-512-256-sess"),
+            HashAlgorithm::Custom(ref algorithm) => write!(f, "{}", algorithm),
+            HashAlgorithm::Unknown(ref name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// The implementation of a hash function: `crypto-hash` does not provide SHA-512/256.
+#[allow(bare_trait_objects)]
+enum Hasher {
+    CryptoHash(crypto_hash::Hasher),
+    SHA512256(Sha512256),
+    Custom(Box<algorithm::Hasher>),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match *self {
+            Hasher::CryptoHash(ref mut hasher) => {
+                hasher.write_all(data).expect("Could not hash data")
+            }
+            Hasher::SHA512256(ref mut hasher) => hasher.update(data),
+            Hasher::Custom(ref mut hasher) => hasher.update(data),
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::CryptoHash(mut hasher) => hasher.finish(),
+            Hasher::SHA512256(hasher) => hasher.finish(),
+            Hasher::Custom(hasher) => hasher.finish(),
         }
     }
 }
 
 impl HashAlgorithm {
-    fn to_algorithm(&self) -> crypto_hash::Algorithm {
+    fn hasher(&self) -> Result<Hasher, Error> {
         match *self {
             HashAlgorithm::MD5 |
-            HashAlgorithm::MD5Session => crypto_hash::Algorithm::MD5,
+            HashAlgorithm::MD5Session => {
+                Ok(Hasher::CryptoHash(crypto_hash::Hasher::new(crypto_hash::Algorithm::MD5)))
+            }
             HashAlgorithm::SHA256 |
-            HashAlgorithm::SHA256Session => crypto_hash::Algorithm::SHA256,
+            HashAlgorithm::SHA256Session => {
+                Ok(Hasher::CryptoHash(crypto_hash::Hasher::new(crypto_hash::Algorithm::SHA256)))
+            }
             HashAlgorithm::SHA512256 |
-            HashAlgorithm::SHA512256Session => crypto_hash::Algorithm::SHA512,
+            HashAlgorithm::SHA512256Session => Ok(Hasher::SHA512256(Sha512256::new())),
+            HashAlgorithm::Custom(ref algorithm) => {
+                Ok(Hasher::Custom(algorithm.function().hasher()))
+            }
+            HashAlgorithm::Unknown(ref name) => Err(Error::UnsupportedAlgorithm(name.clone())),
         }
     }
 
-    /// Generate a hexadecimal representation of the output of a cryptographic hash function, given
-    /// `data` and the algorithm.
-    pub fn hex_digest(&self, data: Vec<u8>) -> String {
-        let mut digest = crypto_hash::hex_digest(self.to_algorithm(), data);
-        if *self == HashAlgorithm::SHA512256 || *self == HashAlgorithm::SHA256Session {
-            digest.truncate(64);
+    /// Whether this is a `-sess` variant, whose A1 value includes the nonce and client nonce.
+    pub fn is_session(&self) -> bool {
+        match *self {
+            HashAlgorithm::MD5Session |
+            HashAlgorithm::SHA256Session |
+            HashAlgorithm::SHA512256Session => true,
+            HashAlgorithm::Custom(ref algorithm) => algorithm.is_session(),
+            _ => false,
+        }
+    }
+
+    /// The algorithm without the `-sess` variant, which is the one that userhashes and simple
+    /// hashed A1 values are generated with.
+    pub fn hash_function(&self) -> HashAlgorithm {
+        match *self {
+            HashAlgorithm::MD5Session => HashAlgorithm::MD5,
+            HashAlgorithm::SHA256Session => HashAlgorithm::SHA256,
+            HashAlgorithm::SHA512256Session => HashAlgorithm::SHA512256,
+            HashAlgorithm::Custom(ref algorithm) => {
+                HashAlgorithm::Custom(algorithm.without_session())
+            }
+            ref algorithm => algorithm.clone(),
         }
+    }
+
+    /// Whether digests can be generated with the algorithm, i.e., it is not `Unknown`.
+    pub fn is_supported(&self) -> bool {
+        match *self {
+            HashAlgorithm::Unknown(_) => false,
+            _ => true,
+        }
+    }
 
-        digest
+    /// Generate a hexadecimal representation of the output of a cryptographic hash function, given
+    /// `data` and the algorithm. Returns an error if the algorithm is `Unknown`.
+    pub fn hex_digest(&self, data: Vec<u8>) -> Result<String, Error> {
+        let mut hasher = self.hasher()?;
+        hasher.update(&data);
+        Ok(hasher.finish().to_hex())
+    }
+}
+
+/// Incrementally hashes an entity body, so that it does not need to be buffered in memory in
+/// order to generate or validate a digest whose `qop` is `auth-int`.
+///
+/// The entity body can be written to the hasher in chunks (for example, via `std::io::copy`).
+/// The result of `hex_digest` can be passed to the `Digest` methods that accept an entity hash.
+pub struct EntityBodyHasher {
+    hasher: Hasher,
+}
+
+impl EntityBodyHasher {
+    /// Creates a hasher for the given algorithm. Returns an error if the algorithm is `Unknown`.
+    pub fn new(algorithm: &HashAlgorithm) -> Result<EntityBodyHasher, Error> {
+        Ok(EntityBodyHasher { hasher: algorithm.hasher()? })
+    }
+
+    /// Hashes the next chunk of the entity body.
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk)
+    }
+
+    /// Generates a hexadecimal representation of the hash of the entire entity body.
+    pub fn hex_digest(self) -> String {
+        self.hasher.finish().to_hex()
+    }
+}
+
+impl Write for EntityBodyHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
@@ -107,7 +226,7 @@ impl FromStr for NonceCount {
     type Err = Error;
     fn from_str(s: &str) -> Result<NonceCount, Error> {
         match Vec::from_hex(s) {
-            Ok(bytes) => {
+            Ok(ref bytes) if bytes.len() == 4 => {
                 let mut count: u32 = 0;
                 count |= (bytes[0] as u32) << 24;
                 count |= (bytes[1] as u32) << 16;
@@ -115,7 +234,7 @@ impl FromStr for NonceCount {
                 count |= bytes[3] as u32;
                 Ok(NonceCount(count))
             }
-            _ => Err(Error::Header),
+            _ => Err(Error::MalformedParameter("nc")),
         }
     }
 }
@@ -132,11 +251,8 @@ impl NonceCount {
     /// Returns an error if the value is not a valid nonce count.
     pub fn from_parameters(map: &HashMap<UniCase<String>, String>)
                            -> Result<Option<NonceCount>, Error> {
-        if let Some(value) = unraveled_map_value(map, "nc") {
-            match NonceCount::from_str(&value[..]) {
-                Ok(count) => Ok(Some(count)),
-                _ => Err(Error::Header),
-            }
+        if let Some(value) = parameter_value(map, "nc") {
+            NonceCount::from_str(&value[..]).map(Some)
         } else {
             Ok(None)
         }
@@ -158,7 +274,7 @@ impl FromStr for Qop {
         match s {
             "auth" => Ok(Qop::Auth),
             "auth-int" => Ok(Qop::AuthInt),
-            _ => Err(Error::Header),
+            _ => Err(Error::MalformedParameter("qop")),
         }
     }
 }
@@ -174,18 +290,138 @@ impl fmt::Display for Qop {
 
 impl Qop {
     /// Extracts a `Qop` object from a map of header parameters.
-    /// Returns an error if the value is not a valid qop value.
+    /// Returns an error if the value is not exactly one valid qop value.
     pub fn from_parameters(map: &HashMap<UniCase<String>, String>) -> Result<Option<Qop>, Error> {
-        if let Some(value) = unraveled_map_value(map, "qop") {
+        if let Some(value) = parameter_value(map, "qop") {
+            Qop::from_str(value.trim()).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Extracts a comma-separated list of `Qop` objects from a map of header parameters, in the
+    /// order that they were specified. Unrecognized qop values are ignored.
+    /// Returns an error if the parameter exists but has no valid qop values.
+    pub fn list_from_parameters(map: &HashMap<UniCase<String>, String>) -> Result<Vec<Qop>, Error> {
+        if let Some(value) = parameter_value(map, "qop") {
             let qops: Vec<Qop> =
-                value[..].split(",").filter_map(|s| Qop::from_str(s).ok()).collect();
+                value[..].split(',').filter_map(|s| Qop::from_str(s.trim()).ok()).collect();
             if qops.is_empty() {
-                return Err(Error::Header);
+                return Err(Error::MalformedParameter("qop"));
             }
-            // TODO: Change Qop usage to use a vec instead of one value.
-            Ok(Some(qops[0].clone()))
+            Ok(qops)
         } else {
-            Ok(None)
+            Ok(vec![])
         }
     }
 }
+
+/// An auth-param, as specified in a header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthParam {
+    /// The parameter name, in the case that it was specified in.
+    pub name: String,
+    /// The value, without the quotes and escapes of a quoted-string.
+    pub value: String,
+    /// Whether the value is serialized as a quoted-string instead of a token.
+    pub quoted: bool,
+}
+
+/// The extension auth-params of a header, i.e., the parameters that are not defined by the RFCs
+/// that the header type implements, in the order that they were specified.
+///
+/// The names of the standard parameters of the header type are reserved, so that extensions
+/// cannot shadow them. Names are case-insensitive. To create an empty collection, use the
+/// `new_extensions` function of the header type (for example, `Digest::new_extensions`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtensionParameters {
+    reserved: &'static Declarations,
+    parameters: Vec<AuthParam>,
+}
+
+impl ExtensionParameters {
+    /// Creates an empty collection for a header type whose standard parameters are `reserved`.
+    ///
+    /// Only for use within this crate, since `Declarations` are private; use the `new_extensions`
+    /// function of the header type instead.
+    #[doc(hidden)]
+    pub fn new(reserved: &'static Declarations) -> ExtensionParameters {
+        ExtensionParameters {
+            reserved: reserved,
+            parameters: vec![],
+        }
+    }
+
+    /// Whether the name is the name of a standard parameter of the header type.
+    pub fn is_reserved(&self, name: &str) -> bool {
+        self.reserved.iter().any(|&(reserved, _)| UniCase(reserved) == UniCase(name))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.parameters.iter().position(|parameter| UniCase(&parameter.name[..]) == UniCase(name))
+    }
+
+    /// Adds a parameter, or replaces the value of the parameter with the same name without
+    /// changing its position. Returns the replaced parameter.
+    ///
+    /// Returns an error if the name is reserved or is not a token.
+    pub fn insert_param(&mut self, parameter: AuthParam) -> Result<Option<AuthParam>, Error> {
+        if !is_token(&parameter.name) {
+            return Err(Error::MalformedParameters);
+        } else if self.is_reserved(&parameter.name) {
+            return Err(Error::ReservedParameter(parameter.name));
+        }
+        match self.position(&parameter.name) {
+            Some(pos) => Ok(Some(mem::replace(&mut self.parameters[pos], parameter))),
+            None => {
+                self.parameters.push(parameter);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Like `insert_param`, but the value is only serialized as a quoted-string if it is not a
+    /// token. Returns the replaced value.
+    pub fn insert(&mut self, name: &str, value: &str) -> Result<Option<String>, Error> {
+        let parameter = AuthParam {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            quoted: !is_token(value),
+        };
+        self.insert_param(parameter).map(|replaced| replaced.map(|parameter| parameter.value))
+    }
+
+    /// The value of the parameter with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name).map(|pos| &self.parameters[pos].value[..])
+    }
+
+    /// Removes the parameter with the given name, returning its value.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.position(name).map(|pos| self.parameters.remove(pos).value)
+    }
+
+    /// The parameters, in order.
+    pub fn iter<'a>(&'a self) -> slice::Iter<'a, AuthParam> {
+        self.parameters.iter()
+    }
+
+    /// The number of parameters.
+    pub fn len(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// Whether there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a ExtensionParameters {
+    type Item = &'a AuthParam;
+    type IntoIter = slice::Iter<'a, AuthParam>;
+
+    fn into_iter(self) -> slice::Iter<'a, AuthParam> {
+        self.parameters.iter()
+    }
+}
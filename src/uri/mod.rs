@@ -0,0 +1,164 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+//! Normalization of request targets, so that the `uri` parameter of a `Digest` can be compared
+//! with the Request-URI of the HTTP request.
+
+use hyper::uri::RequestUri;
+use url::Url;
+
+mod test;
+
+/// Prepended to origin-form request targets, so that they are normalized like absolute ones. They
+/// are not resolved against it, because a path that starts with `//` would become an authority.
+const BASE_URL: &'static str = "http://localhost";
+
+/// The parts of a request target that identify the requested resource, as described in
+/// [RFC 7230, section 5.3](https://tools.ietf.org/html/rfc7230#section-5.3).
+#[derive(Clone, Debug, PartialEq)]
+pub enum RequestTarget {
+    /// The origin-form or the absolute-form. Only the absolute-form has an authority, which
+    /// includes the scheme and the port.
+    Resource {
+        /// The scheme, host and port, e.g. `http://example.org:80`.
+        authority: Option<String>,
+        /// The normalized path.
+        path: String,
+        /// The normalized query string, if it is not empty.
+        query: Option<String>,
+    },
+    /// The authority-form, used by `CONNECT` requests.
+    Authority(String),
+    /// The asterisk-form, used by server-wide `OPTIONS` requests.
+    Asterisk,
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    (byte >= b'A' && byte <= b'Z') || (byte >= b'a' && byte <= b'z') ||
+    (byte >= b'0' && byte <= b'9') || byte == b'-' || byte == b'.' || byte == b'_' ||
+    byte == b'~'
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Normalizes percent-encoding as described in
+/// [RFC 3986, section 6.2.2.2](https://tools.ietf.org/html/rfc3986#section-6.2.2.2): unreserved
+/// characters are decoded, and the hexadecimal digits of every other escape are uppercased.
+/// Non-ASCII characters are percent-encoded as UTF-8, so that they match their escapes.
+pub fn normalize_percent_encoding(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut normalized = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                let decoded = high * 16 + low;
+                if is_unreserved(decoded) {
+                    normalized.push(decoded as char);
+                } else {
+                    normalized.push_str(&format!("%{:02X}", decoded));
+                }
+                i += 3;
+                continue;
+            }
+        }
+        if bytes[i] < 0x80 {
+            normalized.push(bytes[i] as char);
+        } else {
+            normalized.push_str(&format!("%{:02X}", bytes[i]));
+        }
+        i += 1;
+    }
+    normalized
+}
+
+fn from_url(url: &Url, authority: Option<String>) -> RequestTarget {
+    RequestTarget::Resource {
+        authority: authority,
+        path: normalize_percent_encoding(url.path()),
+        query: url.query().and_then(|query| if query.is_empty() {
+            None
+        } else {
+            Some(normalize_percent_encoding(query))
+        }),
+    }
+}
+
+fn from_absolute_url(url: &Url) -> RequestTarget {
+    let authority = format!("{}://{}:{}",
+                            url.scheme(),
+                            url.host_str().unwrap_or("").to_lowercase(),
+                            url.port_or_known_default().unwrap_or(0));
+    from_url(url, Some(authority))
+}
+
+/// Parses a request target, such as the `uri` parameter of a `Digest`.
+pub fn parse(target: &str) -> Option<RequestTarget> {
+    if target == "*" {
+        Some(RequestTarget::Asterisk)
+    } else if target.starts_with('/') {
+        match Url::parse(&format!("{}{}", BASE_URL, target)) {
+            Ok(url) => Some(from_url(&url, None)),
+            Err(_) => None,
+        }
+    } else {
+        match Url::parse(target) {
+            Ok(ref url) if !url.cannot_be_a_base() => Some(from_absolute_url(url)),
+            _ if !target.is_empty() => Some(RequestTarget::Authority(target.to_lowercase())),
+            _ => None,
+        }
+    }
+}
+
+/// Converts the Request-URI of an HTTP request into a request target.
+pub fn from_request_uri(request_uri: &RequestUri) -> Option<RequestTarget> {
+    match *request_uri {
+        RequestUri::AbsolutePath(ref path) => parse(path),
+        RequestUri::AbsoluteUri(ref url) => Some(from_absolute_url(url)),
+        RequestUri::Authority(ref authority) => {
+            Some(RequestTarget::Authority(authority.to_lowercase()))
+        }
+        RequestUri::Star => Some(RequestTarget::Asterisk),
+    }
+}
+
+/// Whether two request targets identify the same resource. The authorities are only compared if
+/// both targets are in absolute-form, because a client can send the `uri` parameter in
+/// origin-form when the Request-URI is in absolute-form, or vice versa.
+pub fn matches(a: &RequestTarget, b: &RequestTarget) -> bool {
+    match (a, b) {
+        (&RequestTarget::Resource { authority: ref authority_a,
+                                    path: ref path_a,
+                                    query: ref query_a },
+         &RequestTarget::Resource { authority: ref authority_b,
+                                    path: ref path_b,
+                                    query: ref query_b }) => {
+            let same_authority = match (authority_a, authority_b) {
+                (&Some(ref authority_a), &Some(ref authority_b)) => authority_a == authority_b,
+                _ => true,
+            };
+            same_authority && path_a == path_b && query_a == query_b
+        }
+        _ => a == b,
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright (c) 2016 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![cfg(test)]
+
+use hyper::uri::RequestUri;
+use super::{RequestTarget, from_request_uri, matches, normalize_percent_encoding, parse};
+use url::Url;
+
+fn assert_matches(a: &str, b: &str) {
+    let a = parse(a).expect("Could not parse request target");
+    let b = parse(b).expect("Could not parse request target");
+    assert!(matches(&a, &b), "{:?} does not match {:?}", a, b)
+}
+
+fn assert_does_not_match(a: &str, b: &str) {
+    let a = parse(a).expect("Could not parse request target");
+    let b = parse(b).expect("Could not parse request target");
+    assert!(!matches(&a, &b), "{:?} matches {:?}", a, b)
+}
+
+#[test]
+fn test_normalize_percent_encoding() {
+    assert_eq!("/~user/a%2Fb%3F", normalize_percent_encoding("/%7euser/a%2fb%3F"));
+    assert_eq!("/100%", normalize_percent_encoding("/100%"));
+    assert_eq!("/%zz", normalize_percent_encoding("/%zz"));
+    assert_eq!("/caf%C3%A9/%E6%97%A5", normalize_percent_encoding("/caf\u{e9}/\u{65e5}"));
+}
+
+#[test]
+fn test_parse_origin_form() {
+    let expected = RequestTarget::Resource {
+        authority: None,
+        path: "/dir/index.html".to_owned(),
+        query: Some("a=b".to_owned()),
+    };
+    assert_eq!(Some(expected), parse("/dir/index.html?a=b"))
+}
+
+#[test]
+fn test_parse_origin_form_with_empty_first_segment() {
+    let expected = RequestTarget::Resource {
+        authority: None,
+        path: "//evil/x".to_owned(),
+        query: None,
+    };
+    assert_eq!(Some(expected), parse("//evil/x"));
+    assert_does_not_match("/x", "//evil/x")
+}
+
+#[test]
+fn test_parse_absolute_form() {
+    let expected = RequestTarget::Resource {
+        authority: Some("http://example.org:80".to_owned()),
+        path: "/dir/index.html".to_owned(),
+        query: None,
+    };
+    assert_eq!(Some(expected), parse("HTTP://Example.org/dir/index.html?"))
+}
+
+#[test]
+fn test_parse_authority_and_asterisk_forms() {
+    assert_eq!(Some(RequestTarget::Authority("example.org:443".to_owned())),
+               parse("Example.org:443"));
+    assert_eq!(Some(RequestTarget::Asterisk), parse("*"));
+    assert_eq!(None, parse(""))
+}
+
+#[test]
+fn test_matches_origin_and_absolute_forms() {
+    assert_matches("/dir/index.html", "http://example.org/dir/index.html");
+    assert_matches("http://example.org:80/dir/index.html", "http://EXAMPLE.org/dir/index.html");
+}
+
+#[test]
+fn test_matches_with_percent_encoding() {
+    assert_matches("/%7Euser/caf%c3%a9", "/~user/caf%C3%A9");
+    assert_matches("/a b", "/a%20b");
+    assert_matches("/caf\u{e9}", "/caf%C3%A9");
+    assert_does_not_match("/a%2Fb", "/a/b");
+}
+
+#[test]
+fn test_matches_with_dot_segments() {
+    assert_matches("/dir/../dir/./index.html", "/dir/index.html")
+}
+
+#[test]
+fn test_does_not_match_other_resources() {
+    assert_does_not_match("/dir/index.html", "/dir/other.html");
+    assert_does_not_match("/dir/index.html?a=b", "/dir/index.html?a=c");
+    assert_does_not_match("/dir/index.html?a=b", "/dir/index.html");
+    assert_does_not_match("http://example.org/dir/index.html",
+                          "http://example.com/dir/index.html");
+    assert_does_not_match("http://example.org/dir/index.html",
+                          "https://example.org/dir/index.html");
+    assert_does_not_match("*", "/");
+}
+
+#[test]
+fn test_from_request_uri() {
+    let url = Url::parse("http://example.org/dir/index.html").expect("Could not parse URL");
+    assert_eq!(parse("http://example.org/dir/index.html"),
+               from_request_uri(&RequestUri::AbsoluteUri(url)));
+    assert_eq!(parse("/dir/index.html"),
+               from_request_uri(&RequestUri::AbsolutePath("/dir/index.html".to_owned())));
+    assert_eq!(parse("example.org:443"),
+               from_request_uri(&RequestUri::Authority("example.org:443".to_owned())));
+    assert_eq!(Some(RequestTarget::Asterisk), from_request_uri(&RequestUri::Star))
+}
@@ -1,14 +1,18 @@
 //! An implementation of the `WWW-Authenticate` header.
 
-use hyper::header::Header;
+use basic::BasicChallenge;
+use error::Error;
+use hyper::header::{Charset, Header, HeaderFormat};
 use hyper::header::parsing::from_one_raw_str;
 use hyper::{Error as HyperError, Result as HyperResult};
-use parsing::{parse_parameters, unraveled_map_value};
-use super::types::{HashAlgorithm, Qop};
+use parsing::{append_extensions, append_parameter, parameter_value, parse_charset, parse_parameters,
+              split_challenges, strip_scheme, Declarations, Decoding};
+use super::types::{ExtensionParameters, HashAlgorithm, Qop};
 use unicase::UniCase;
 
 use std::collections::HashMap;
-use std::str::FromStr;
+use std::fmt;
+use std::str::{self, FromStr};
 use std::iter::FromIterator;
 
 mod test;
@@ -23,7 +27,7 @@ mod test;
 pub struct WwwAuthenticate {
     /// Authentication realm.
     pub realm: String,
-    /// Optional comma-separated list of URIs
+    /// Optional space-separated list of URIs that define the protection space.
     pub domain: Vec<String>,
     /// Cryptographic nonce.
     pub nonce: String,
@@ -33,59 +37,87 @@ pub struct WwwAuthenticate {
     pub stale: bool,
     /// The hash algorithm to use when generating the `response`.
     pub algorithm: HashAlgorithm,
-    /// Quality of protection. Optional only in RFC 2067 mode.
-    pub qop: Option<Qop>,
+    /// The qualities of protection that the server supports, in order of preference. Empty only
+    /// in RFC 2069 mode.
+    pub qop: Vec<Qop>,
     /// The character set to use when generating the A1 value or the userhash. Added for RFC 7616.
-    pub charset: Option<String>,
+    pub charset: Option<Charset>,
     /// Whether `username` is a userhash. Added for RFC 7616.
     pub userhash: bool,
+    /// Extension parameters, which are not defined by RFC 7616.
+    pub extensions: ExtensionParameters,
 }
 
+/// The parameters of a `Digest` challenge, as described in
+/// [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3).
+const PARAMETERS: &'static Declarations = &[("realm", Decoding::QuotedString),
+                                             ("domain", Decoding::QuotedString),
+                                             ("nonce", Decoding::QuotedString),
+                                             ("opaque", Decoding::QuotedString),
+                                             ("stale", Decoding::Token),
+                                             ("algorithm", Decoding::Token),
+                                             ("qop", Decoding::QuotedString),
+                                             ("charset", Decoding::Token),
+                                             ("userhash", Decoding::Token)];
+
 fn parse_domain(parameters: &HashMap<UniCase<String>, String>) -> Vec<String> {
-    match unraveled_map_value(parameters, "domain") {
-        Some(value) => Vec::from_iter(value.split(",").map(str::trim).map(String::from)),
+    match parameter_value(parameters, "domain") {
+        Some(value) => {
+            Vec::from_iter(value.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|uri| !uri.is_empty())
+                .map(String::from))
+        }
         None => vec![],
     }
 }
 
 fn parse_bool(parameters: &HashMap<UniCase<String>, String>,
-              name: &str)
-              -> Result<bool, HyperError> {
-    let value = unraveled_map_value(parameters, name);
+              name: &'static str)
+              -> Result<bool, Error> {
+    let value = parameter_value(parameters, name);
     match value {
         Some(s) => {
             match &s[..] {
                 "true" => Ok(true),
                 "false" => Ok(false),
-                _ => Err(HyperError::Header),
+                _ => Err(Error::MalformedParameter(name)),
             }
         }
         None => Ok(false),
     }
 }
 
+impl WwwAuthenticate {
+    /// Creates an empty collection of extension parameters, in which the parameters of RFC 7616
+    /// are reserved.
+    pub fn new_extensions() -> ExtensionParameters {
+        ExtensionParameters::new(PARAMETERS)
+    }
+}
+
 impl FromStr for WwwAuthenticate {
-    type Err = HyperError;
+    type Err = Error;
 
-    fn from_str(s: &str) -> Result<WwwAuthenticate, HyperError> {
-        let parameters = parse_parameters(s);
-        let realm = match unraveled_map_value(&parameters, "realm") {
+    fn from_str(s: &str) -> Result<WwwAuthenticate, Error> {
+        let value = strip_scheme(s, "Digest").unwrap_or(s);
+        let (parameters, extensions) = parse_parameters(value, PARAMETERS)?;
+        let realm = match parameter_value(&parameters, "realm") {
             Some(value) => value,
-            None => return Err(HyperError::Header),
+            None => return Err(Error::MissingParameter("realm")),
         };
         let domain = parse_domain(&parameters);
-        let nonce = match unraveled_map_value(&parameters, "nonce") {
+        let nonce = match parameter_value(&parameters, "nonce") {
             Some(value) => value,
-            None => return Err(HyperError::Header),
+            None => return Err(Error::MissingParameter("nonce")),
         };
-        let opaque = unraveled_map_value(&parameters, "opaque");
+        let opaque = parameter_value(&parameters, "opaque");
         let stale: bool = parse_bool(&parameters, "stale")?;
-        let algorithm = match unraveled_map_value(&parameters, "algorithm") {
+        let algorithm = match parameter_value(&parameters, "algorithm") {
             Some(s) => HashAlgorithm::from_str(&s)?,
             None => HashAlgorithm::MD5,
         };
-        let qop = Qop::from_parameters(&parameters)?;
-        let charset = unraveled_map_value(&parameters, "charset");
+        let qop = Qop::list_from_parameters(&parameters)?;
+        let charset = parse_charset(&parameters)?;
         let userhash = parse_bool(&parameters, "userhash")?;
 
         Ok(WwwAuthenticate {
@@ -98,6 +130,7 @@ impl FromStr for WwwAuthenticate {
             qop: qop,
             charset: charset,
             userhash: userhash,
+            extensions: extensions,
         })
     }
 }
@@ -109,6 +142,227 @@ impl Header for WwwAuthenticate {
     }
 
     fn parse_header(raw: &[Vec<u8>]) -> HyperResult<WwwAuthenticate> {
-        from_one_raw_str(raw).and_then(|s: String| WwwAuthenticate::from_str(&s[..]))
+        from_one_raw_str(raw)
+            .and_then(|s: String| WwwAuthenticate::from_str(&s[..]).map_err(HyperError::from))
+    }
+}
+
+impl HeaderFormat for WwwAuthenticate {
+    fn fmt_header(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut serialized = String::new();
+        append_parameter(&mut serialized, "realm", &self.realm, true);
+        if !self.domain.is_empty() {
+            append_parameter(&mut serialized, "domain", &self.domain.join(" "), true);
+        }
+        if !self.qop.is_empty() {
+            let qop: Vec<String> = self.qop.iter().map(Qop::to_string).collect();
+            append_parameter(&mut serialized, "qop", &qop.join(", "), true);
+        }
+        append_parameter(&mut serialized,
+                         "algorithm",
+                         &self.algorithm.to_string(),
+                         false);
+        append_parameter(&mut serialized, "nonce", &self.nonce, true);
+        if let Some(ref opaque) = self.opaque {
+            append_parameter(&mut serialized, "opaque", opaque, true);
+        }
+        if self.stale {
+            append_parameter(&mut serialized, "stale", "true", false);
+        }
+        if let Some(ref charset) = self.charset {
+            append_parameter(&mut serialized, "charset", &charset.to_string(), false);
+        }
+        if self.userhash {
+            append_parameter(&mut serialized, "userhash", "true", false);
+        }
+        append_extensions(&mut serialized, &self.extensions);
+        write!(f, "Digest {}", serialized)
+    }
+}
+
+/// A single challenge from a `WWW-Authenticate` header.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Challenge {
+    /// A challenge for the `Digest` scheme.
+    Digest(WwwAuthenticate),
+    /// A challenge for the `Basic` scheme.
+    Basic(BasicChallenge),
+    /// A challenge for an unsupported auth-scheme: the auth-scheme, followed by its unparsed
+    /// parameters (or token68).
+    Other(String, String),
+}
+
+impl FromStr for Challenge {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Challenge, Error> {
+        let challenge = s.trim();
+        let (scheme, parameters) = match challenge.find(char::is_whitespace) {
+            Some(pos) => (&challenge[..pos], challenge[pos..].trim()),
+            None => (challenge, ""),
+        };
+        if UniCase(scheme) == UniCase("Digest") {
+            Ok(Challenge::Digest(WwwAuthenticate::from_str(parameters)?))
+        } else if UniCase(scheme) == UniCase("Basic") {
+            Ok(Challenge::Basic(BasicChallenge::from_str(challenge)?))
+        } else if scheme.is_empty() {
+            Err(Error::MissingChallenge)
+        } else {
+            Ok(Challenge::Other(scheme.to_owned(), parameters.to_owned()))
+        }
+    }
+}
+
+impl fmt::Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Challenge::Digest(ref challenge) => challenge.fmt_header(f),
+            Challenge::Basic(ref challenge) => challenge.fmt_header(f),
+            Challenge::Other(ref scheme, ref parameters) if parameters.is_empty() => {
+                write!(f, "{}", scheme)
+            }
+            Challenge::Other(ref scheme, ref parameters) => write!(f, "{} {}", scheme, parameters),
+        }
+    }
+}
+
+/// Ranks the hash algorithms by the strength of the underlying hash function. Unknown algorithms
+/// cannot be used, so they are not ranked.
+fn algorithm_strength(algorithm: &HashAlgorithm) -> Option<u8> {
+    match *algorithm {
+        HashAlgorithm::MD5 |
+        HashAlgorithm::MD5Session => Some(0),
+        HashAlgorithm::SHA256 |
+        HashAlgorithm::SHA256Session => Some(1),
+        HashAlgorithm::SHA512256 |
+        HashAlgorithm::SHA512256Session => Some(2),
+        HashAlgorithm::Custom(ref algorithm) => Some(algorithm.function().strength()),
+        HashAlgorithm::Unknown(_) => None,
+    }
+}
+
+/// Every challenge in one or more `WWW-Authenticate` headers, in the order that the server sent
+/// them.
+///
+/// As described in [RFC 7235, section 4.1](https://tools.ietf.org/html/rfc7235#section-4.1) and
+/// [RFC 7616, section 3.7](https://tools.ietf.org/html/rfc7616#section-3.7), a server can offer
+/// several challenges, for example one per hash algorithm that it supports.
+///
+/// Malformed challenges are skipped, so that the client can still respond to the others. Parsing
+/// only fails if none of the challenges can be parsed.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Challenges(pub Vec<Challenge>);
+
+impl Challenges {
+    /// The `Digest` challenges, in the order that the server sent them.
+    pub fn digest(&self) -> Vec<&WwwAuthenticate> {
+        self.0
+            .iter()
+            .filter_map(|challenge| match *challenge {
+                Challenge::Digest(ref digest) => Some(digest),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The `Digest` challenge with the strongest hash algorithm. If several challenges use equally
+    /// strong algorithms, the first one is returned. Challenges with unknown algorithms are
+    /// skipped, so they should be resolved via `AlgorithmRegistry::resolve` first.
+    pub fn strongest_digest(&self) -> Option<&WwwAuthenticate> {
+        let mut strongest: Option<(&WwwAuthenticate, u8)> = None;
+        for challenge in self.digest() {
+            let strength = match algorithm_strength(&challenge.algorithm) {
+                Some(strength) => strength,
+                None => continue,
+            };
+            strongest = match strongest {
+                Some((current, current_strength)) if current_strength >= strength => {
+                    Some((current, current_strength))
+                }
+                _ => Some((challenge, strength)),
+            };
+        }
+        strongest.map(|(challenge, _)| challenge)
+    }
+
+    /// Creates a `Digest` challenge for each of the algorithms, which are otherwise identical to
+    /// `challenge`. Servers can use this to offer every algorithm in an `AlgorithmRegistry`.
+    pub fn for_algorithms(challenge: &WwwAuthenticate, algorithms: &[HashAlgorithm]) -> Challenges {
+        Challenges(algorithms.iter()
+            .map(|algorithm| {
+                let mut challenge = challenge.clone();
+                challenge.algorithm = algorithm.clone();
+                Challenge::Digest(challenge)
+            })
+            .collect())
+    }
+
+    /// The first `Basic` challenge, if the server sent one.
+    pub fn basic(&self) -> Option<&BasicChallenge> {
+        self.0
+            .iter()
+            .filter_map(|challenge| match *challenge {
+                Challenge::Basic(ref basic) => Some(basic),
+                _ => None,
+            })
+            .next()
+    }
+}
+
+impl FromStr for Challenges {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Challenges, Error> {
+        let mut challenges = Vec::new();
+        let mut first_error = None;
+        for challenge in split_challenges(s) {
+            match Challenge::from_str(challenge) {
+                Ok(challenge) => challenges.push(challenge),
+                Err(error) => {
+                    if first_error.is_none() {
+                        first_error = Some(error);
+                    }
+                }
+            }
+        }
+
+        if challenges.is_empty() {
+            Err(first_error.unwrap_or(Error::MissingChallenge))
+        } else {
+            Ok(Challenges(challenges))
+        }
+    }
+}
+
+impl Header for Challenges {
+    fn header_name() -> &'static str {
+        "WWW-Authenticate"
+    }
+
+    fn parse_header(raw: &[Vec<u8>]) -> HyperResult<Challenges> {
+        let mut challenges = Vec::new();
+        for line in raw {
+            if let Ok(Challenges(mut parsed)) = Challenges::from_str(str::from_utf8(line)?) {
+                challenges.append(&mut parsed);
+            }
+        }
+
+        if challenges.is_empty() {
+            Err(HyperError::Header)
+        } else {
+            Ok(Challenges(challenges))
+        }
+    }
+}
+
+impl HeaderFormat for Challenges {
+    fn fmt_header(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, challenge) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", challenge)?;
+        }
+        Ok(())
     }
 }
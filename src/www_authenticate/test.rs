@@ -1,7 +1,12 @@
 #![cfg(test)]
 
-use parsing::test_helper::assert_parsed_header_equal;
-use super::WwwAuthenticate;
+use basic::BasicChallenge;
+use error::Error;
+use hyper::header::{Charset, Header};
+use parsing::test_helper::{assert_header_parsing_error, assert_parsed_header_equal,
+                           assert_serialized_header_equal};
+use std::str::FromStr;
+use super::{Challenge, Challenges, WwwAuthenticate};
 use super::super::types::{HashAlgorithm, Qop};
 
 #[test]
@@ -13,9 +18,10 @@ fn test_parse_authentication_info_with_digest_and_nextnonce() {
         opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".to_owned()),
         stale: false,
         algorithm: HashAlgorithm::MD5,
-        qop: None,
+        qop: vec![],
         charset: None,
         userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
     };
     assert_parsed_header_equal(expected,
                                "realm=\"testrealm@host.com\", \
@@ -24,7 +30,6 @@ fn test_parse_authentication_info_with_digest_and_nextnonce() {
 }
 
 #[test]
-/// TODO: fix qop
 fn test_parse_www_authenticate_rfc7616_3_9_1_shasum() {
     let expected = WwwAuthenticate {
         realm: "http-auth@example.org".to_owned(),
@@ -33,9 +38,10 @@ fn test_parse_www_authenticate_rfc7616_3_9_1_shasum() {
         opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
         stale: false,
         algorithm: HashAlgorithm::SHA256,
-        qop: Some(Qop::Auth),
+        qop: vec![Qop::Auth, Qop::AuthInt],
         charset: None,
         userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
     };
     assert_parsed_header_equal(expected,
                                "realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
@@ -45,7 +51,6 @@ fn test_parse_www_authenticate_rfc7616_3_9_1_shasum() {
 }
 
 /// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.1
-/// TODO: fix qop
 #[test]
 fn test_parse_www_authenticate_rfc7616_3_9_1_md5() {
     let expected = WwwAuthenticate {
@@ -55,9 +60,10 @@ fn test_parse_www_authenticate_rfc7616_3_9_1_md5() {
         opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
         stale: false,
         algorithm: HashAlgorithm::MD5,
-        qop: Some(Qop::Auth),
+        qop: vec![Qop::Auth, Qop::AuthInt],
         charset: None,
         userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
     };
     assert_parsed_header_equal(expected,
                                "realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
@@ -76,9 +82,10 @@ fn test_parse_www_authenticate_rfc7616_3_9_2() {
         opaque: Some("HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS".to_owned()),
         stale: false,
         algorithm: HashAlgorithm::SHA512256,
-        qop: Some(Qop::Auth),
-        charset: Some("UTF-8".to_owned()),
+        qop: vec![Qop::Auth],
+        charset: Some(Charset::Ext("UTF-8".to_owned())),
         userhash: true,
+        extensions: WwwAuthenticate::new_extensions(),
     };
     assert_parsed_header_equal(expected,
                                "realm=\"api@example.org\", qop=\"auth\", algorithm=SHA-512-256, \
@@ -86,3 +93,354 @@ fn test_parse_www_authenticate_rfc7616_3_9_2() {
                                 opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
                                 charset=UTF-8, userhash=true")
 }
+
+fn rfc7616_3_9_2_challenge() -> WwwAuthenticate {
+    WwwAuthenticate {
+        realm: "api@example.org".to_owned(),
+        domain: vec![],
+        nonce: "5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK".to_owned(),
+        opaque: Some("HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS".to_owned()),
+        stale: false,
+        algorithm: HashAlgorithm::SHA512256,
+        qop: vec![Qop::Auth],
+        charset: Some(Charset::Ext("UTF-8".to_owned())),
+        userhash: true,
+        extensions: WwwAuthenticate::new_extensions(),
+    }
+}
+
+#[test]
+fn test_parse_www_authenticate_with_scheme() {
+    assert_parsed_header_equal(rfc7616_3_9_2_challenge(),
+                               "Digest realm=\"api@example.org\", qop=\"auth\", \
+                                algorithm=SHA-512-256, \
+                                nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+                                opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
+                                charset=UTF-8, userhash=true")
+}
+
+#[test]
+fn test_parse_www_authenticate_with_domain() {
+    let mut expected = rfc7616_3_9_2_challenge();
+    expected.domain = vec!["/doe.json".to_owned(), "https://api.example.org/".to_owned()];
+    assert_parsed_header_equal(expected,
+                               "Digest realm=\"api@example.org\", \
+                                domain=\"/doe.json https://api.example.org/\", qop=\"auth\", \
+                                algorithm=SHA-512-256, \
+                                nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+                                opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
+                                charset=UTF-8, userhash=true")
+}
+
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.1
+#[test]
+fn test_fmt_www_authenticate_rfc7616_3_9_1() {
+    let header = WwwAuthenticate {
+        realm: "http-auth@example.org".to_owned(),
+        domain: vec![],
+        nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v".to_owned(),
+        opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
+        stale: false,
+        algorithm: HashAlgorithm::SHA256,
+        qop: vec![Qop::Auth, Qop::AuthInt],
+        charset: None,
+        userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
+    };
+    assert_serialized_header_equal(header,
+                                   "WWW-Authenticate: Digest realm=\"http-auth@example.org\", \
+                                    qop=\"auth, auth-int\", algorithm=SHA-256, \
+                                    nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                                    opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"")
+}
+
+#[test]
+fn test_fmt_www_authenticate_with_domain_and_stale() {
+    let header = WwwAuthenticate {
+        realm: "testrealm@host.com".to_owned(),
+        domain: vec!["/dir/".to_owned(), "http://example.com/".to_owned()],
+        nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_owned(),
+        opaque: None,
+        stale: true,
+        algorithm: HashAlgorithm::MD5,
+        qop: vec![],
+        charset: None,
+        userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
+    };
+    assert_serialized_header_equal(header,
+                                   "WWW-Authenticate: Digest realm=\"testrealm@host.com\", \
+                                    domain=\"/dir/ http://example.com/\", algorithm=MD5, \
+                                    nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", stale=true")
+}
+
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.2
+#[test]
+fn test_www_authenticate_round_trip_rfc7616_3_9_2() {
+    let data = "Digest realm=\"api@example.org\", qop=\"auth\", algorithm=SHA-512-256, \
+                nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+                opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", charset=UTF-8, \
+                userhash=true";
+    let header: WwwAuthenticate = Header::parse_header(&[data.to_owned().into_bytes()][..])
+        .expect("Could not parse WWW-Authenticate header");
+    assert_eq!(rfc7616_3_9_2_challenge(), header);
+    assert_serialized_header_equal(header, &format!("WWW-Authenticate: {}", data)[..])
+}
+
+fn rfc7616_3_7_challenge(algorithm: HashAlgorithm) -> Challenge {
+    Challenge::Digest(WwwAuthenticate {
+        realm: "http-auth@example.org".to_owned(),
+        domain: vec![],
+        nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v".to_owned(),
+        opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
+        stale: false,
+        algorithm: algorithm,
+        qop: vec![Qop::Auth, Qop::AuthInt],
+        charset: None,
+        userhash: false,
+        extensions: WwwAuthenticate::new_extensions(),
+    })
+}
+
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.7
+#[test]
+fn test_parse_challenges_from_multiple_headers() {
+    let expected = Challenges(vec![rfc7616_3_7_challenge(HashAlgorithm::SHA256),
+                                   rfc7616_3_7_challenge(HashAlgorithm::MD5)]);
+    let sha256 = b"Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                   algorithm=SHA-256, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                   opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        .to_vec();
+    let md5 = b"Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=MD5, \
+                nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        .to_vec();
+    let actual: Challenges = Header::parse_header(&[sha256, md5][..])
+        .expect("Could not parse challenges");
+    assert_eq!(expected, actual)
+}
+
+#[test]
+fn test_parse_challenges_from_one_header() {
+    let expected = Challenges(vec![rfc7616_3_7_challenge(HashAlgorithm::SHA256),
+                                   rfc7616_3_7_challenge(HashAlgorithm::MD5),
+                                   Challenge::Basic(BasicChallenge {
+                                       realm: "simple".to_owned(),
+                                       charset: Some(Charset::Ext("UTF-8".to_owned())),
+                                   })]);
+    assert_parsed_header_equal(expected,
+                               "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                                algorithm=SHA-256, \
+                                nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                                opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
+                                Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                                algorithm=MD5, \
+                                nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                                opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
+                                Basic realm=\"simple\", charset=\"UTF-8\"")
+}
+
+/// Cf. https://tools.ietf.org/html/rfc7235#section-4.1
+#[test]
+fn test_parse_challenges_with_unsupported_scheme() {
+    let expected = Challenges(vec![Challenge::Other("Newauth".to_owned(),
+                                                    "realm=\"apps\", type=1, \
+                                                     title=\"Login to \\\"apps\\\"\""
+                                                        .to_owned()),
+                                   Challenge::Basic(BasicChallenge {
+                                       realm: "simple".to_owned(),
+                                       charset: None,
+                                   })]);
+    assert_parsed_header_equal(expected,
+                               "Newauth realm=\"apps\", type=1, \
+                                title=\"Login to \\\"apps\\\"\", Basic realm=\"simple\"")
+}
+
+#[test]
+fn test_parse_challenges_with_token68_and_bare_scheme() {
+    let expected = Challenges(vec![Challenge::Other("Negotiate".to_owned(), "".to_owned()),
+                                   Challenge::Other("Bearer".to_owned(), "abc+/def==".to_owned())]);
+    assert_parsed_header_equal(expected, "Negotiate, Bearer abc+/def==")
+}
+
+#[test]
+fn test_parse_challenges_with_invalid_digest_challenge() {
+    let basic = Challenge::Basic(BasicChallenge {
+        realm: "simple".to_owned(),
+        charset: None,
+    });
+    assert_parsed_header_equal(Challenges(vec![basic]),
+                               "Digest nonce=\"abc\", Basic realm=\"simple\"")
+}
+
+#[test]
+fn test_parse_challenges_with_invalid_basic_challenge() {
+    let expected = Challenges(vec![Challenge::Digest(WwwAuthenticate {
+                                       realm: "a".to_owned(),
+                                       domain: vec![],
+                                       nonce: "n".to_owned(),
+                                       opaque: None,
+                                       stale: false,
+                                       algorithm: HashAlgorithm::MD5,
+                                       qop: vec![],
+                                       charset: None,
+                                       userhash: false,
+                                       extensions: WwwAuthenticate::new_extensions(),
+                                   })]);
+    assert_parsed_header_equal(expected, "Basic, Digest realm=\"a\", nonce=\"n\"")
+}
+
+#[test]
+fn test_parse_challenges_sans_valid_challenge() {
+    assert_header_parsing_error::<Challenges>("Digest nonce=\"abc\", Basic")
+}
+
+#[test]
+fn test_parse_challenges_sans_scheme() {
+    assert_header_parsing_error::<Challenges>("realm=\"simple\"")
+}
+
+#[test]
+fn test_fmt_challenges() {
+    let challenges = Challenges(vec![rfc7616_3_7_challenge(HashAlgorithm::SHA256),
+                                     Challenge::Basic(BasicChallenge {
+                                         realm: "simple".to_owned(),
+                                         charset: None,
+                                     }),
+                                     Challenge::Other("Negotiate".to_owned(), "".to_owned())]);
+    assert_serialized_header_equal(challenges,
+                                   "WWW-Authenticate: Digest realm=\"http-auth@example.org\", \
+                                    qop=\"auth, auth-int\", algorithm=SHA-256, \
+                                    nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                                    opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
+                                    Basic realm=\"simple\", Negotiate")
+}
+
+#[test]
+fn test_strongest_digest() {
+    let challenges = Challenges(vec![rfc7616_3_7_challenge(HashAlgorithm::MD5),
+                                     rfc7616_3_7_challenge(HashAlgorithm::SHA256),
+                                     rfc7616_3_7_challenge(HashAlgorithm::MD5Session),
+                                     rfc7616_3_7_challenge(HashAlgorithm::SHA256Session)]);
+    assert_eq!(4, challenges.digest().len());
+    let strongest = challenges.strongest_digest().expect("No digest challenge found");
+    assert_eq!(HashAlgorithm::SHA256, strongest.algorithm)
+}
+
+#[test]
+fn test_strongest_digest_skips_unknown_algorithms() {
+    let sha3 = HashAlgorithm::Unknown("SHA3-256".to_owned());
+    let challenges = Challenges(vec![rfc7616_3_7_challenge(sha3.clone()),
+                                     rfc7616_3_7_challenge(HashAlgorithm::MD5)]);
+    let strongest = challenges.strongest_digest().expect("No digest challenge found");
+    assert_eq!(HashAlgorithm::MD5, strongest.algorithm);
+
+    let unknown = Challenges(vec![rfc7616_3_7_challenge(sha3)]);
+    assert_eq!(None, unknown.strongest_digest())
+}
+
+#[test]
+fn test_challenges_for_algorithms() {
+    let template = match rfc7616_3_7_challenge(HashAlgorithm::MD5) {
+        Challenge::Digest(challenge) => challenge,
+        _ => unreachable!(),
+    };
+    let expected = Challenges(vec![rfc7616_3_7_challenge(HashAlgorithm::SHA256),
+                                   rfc7616_3_7_challenge(HashAlgorithm::MD5)]);
+    assert_eq!(expected,
+               Challenges::for_algorithms(&template,
+                                          &[HashAlgorithm::SHA256, HashAlgorithm::MD5]))
+}
+
+#[test]
+fn test_parse_www_authenticate_with_unknown_algorithm() {
+    let data = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                algorithm=SHA3-256, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    let header: WwwAuthenticate = Header::parse_header(&[data.to_owned().into_bytes()][..])
+        .expect("Could not parse WWW-Authenticate header");
+    assert_eq!(HashAlgorithm::Unknown("SHA3-256".to_owned()), header.algorithm);
+    assert_serialized_header_equal(header, &format!("WWW-Authenticate: {}", data)[..])
+}
+
+#[test]
+fn test_strongest_digest_sans_digest_challenges() {
+    let challenges = Challenges(vec![Challenge::Other("Negotiate".to_owned(), "".to_owned())]);
+    assert_eq!(None, challenges.strongest_digest());
+    assert_eq!(None, challenges.basic())
+}
+
+/// Cf. https://tools.ietf.org/html/rfc7616#section-3.9.1
+#[test]
+fn test_www_authenticate_round_trip_rfc7616_3_9_1() {
+    let data = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                algorithm=SHA-256, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    let header: WwwAuthenticate = Header::parse_header(&[data.to_owned().into_bytes()][..])
+        .expect("Could not parse WWW-Authenticate header");
+    assert_eq!(vec![Qop::Auth, Qop::AuthInt], header.qop);
+    assert_serialized_header_equal(header, &format!("WWW-Authenticate: {}", data)[..])
+}
+
+#[test]
+fn test_www_authenticate_round_trip_with_escaped_realm() {
+    let data = "Digest realm=\"\\\"quoted\\\" \\\\ realm\", algorithm=MD5, \
+                nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\"";
+    let header: WwwAuthenticate = Header::parse_header(&[data.to_owned().into_bytes()][..])
+        .expect("Could not parse WWW-Authenticate header");
+    assert_eq!("\"quoted\" \\ realm", header.realm);
+    assert_serialized_header_equal(header, &format!("WWW-Authenticate: {}", data)[..])
+}
+
+#[test]
+fn test_www_authenticate_round_trip_with_extensions() {
+    let data = "Digest realm=\"http-auth@example.org\", algorithm=MD5, \
+                nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                x-vendor-hint=\"quoted \\\"value\\\"\", x-vendor-flag=1, x-vendor-token=\"tok\"";
+    let header: WwwAuthenticate = Header::parse_header(&[data.to_owned().into_bytes()][..])
+        .expect("Could not parse WWW-Authenticate header");
+    let names: Vec<&str> = header.extensions.iter().map(|p| &p.name[..]).collect();
+    assert_eq!(vec!["x-vendor-hint", "x-vendor-flag", "x-vendor-token"], names);
+    assert_eq!(Some("quoted \"value\""), header.extensions.get("x-vendor-hint"));
+    assert_serialized_header_equal(header, &format!("WWW-Authenticate: {}", data)[..])
+}
+
+#[test]
+fn test_parse_www_authenticate_with_unknown_qop() {
+    let mut expected = rfc7616_3_9_2_challenge();
+    expected.qop = vec![Qop::AuthInt];
+    assert_parsed_header_equal(expected,
+                               "Digest realm=\"api@example.org\", qop=\"auth-conf,auth-int\", \
+                                algorithm=SHA-512-256, \
+                                nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+                                opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
+                                charset=UTF-8, userhash=true")
+}
+
+#[test]
+fn test_parse_www_authenticate_with_no_valid_qop() {
+    assert_header_parsing_error::<WwwAuthenticate>("Digest realm=\"api@example.org\", \
+                                                    qop=\"auth-conf\", \
+                                                    nonce=\"5TsQWLVdgBdmrQ0XsxbDODV\"")
+}
+
+#[test]
+fn test_parse_www_authenticate_errors() {
+    assert_eq!(Err(Error::MissingParameter("nonce")),
+               WwwAuthenticate::from_str("Digest realm=\"api@example.org\""));
+    assert_eq!(Err(Error::MalformedParameter("stale")),
+               WwwAuthenticate::from_str("Digest realm=\"api@example.org\", nonce=\"abc\", \
+                                          stale=maybe"));
+    assert_eq!(Err(Error::UnsupportedCharset("ISO-8859-1".to_owned())),
+               WwwAuthenticate::from_str("Digest realm=\"api@example.org\", nonce=\"abc\", \
+                                          charset=ISO-8859-1"));
+    assert_eq!(Err(Error::MissingChallenge), Challenges::from_str(" , "))
+}
+
+#[test]
+fn test_parse_www_authenticate_with_lowercase_charset() {
+    let challenge = WwwAuthenticate::from_str("Digest realm=\"api@example.org\", nonce=\"abc\", \
+                                               charset=utf-8")
+        .expect("Could not parse challenge");
+    assert_eq!(Some(Charset::Ext("UTF-8".to_owned())), challenge.charset)
+}